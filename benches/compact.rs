@@ -0,0 +1,191 @@
+// Compares the compact board (see src/compact.rs) with the Playfield functions in game_logic on
+// what a search does most: checking which moves are legal, listing them, making them and taking
+// them back, and playing random games.
+//
+// Run with "cargo bench".
+
+#[macro_use]
+extern crate criterion;
+extern crate shenzen_solitaire;
+
+use criterion::Criterion;
+use shenzen_solitaire::compact::*;
+use shenzen_solitaire::game_logic::*;
+use shenzen_solitaire::solver::*;
+use std::hint::black_box;
+
+const POSITIONS: [Position; 15] = [
+    Position::FreeCell(0), Position::FreeCell(1), Position::FreeCell(2), Position::Flower,
+    Position::Pile(0), Position::Pile(1), Position::Pile(2),
+    Position::Tableau(0), Position::Tableau(1), Position::Tableau(2), Position::Tableau(3),
+    Position::Tableau(4), Position::Tableau(5), Position::Tableau(6), Position::Tableau(7),
+];
+
+const SUITS: [Suit; 3] = [Suit::Red, Suit::Green, Suit::Black];
+
+// Calls @f with every action that could be tried on a playfield whose tableau columns are as tall
+// as @column_len says. Moves from the tableau pick up anything from one card to the whole column.
+fn for_each_candidate<L: Fn(usize) -> usize, F: FnMut(Action)>(column_len: L, mut f: F) {
+    for &suit in &SUITS {
+        f(Action::FlipDragon(suit));
+    }
+    for &from in &POSITIONS {
+        let most = match from {
+            Position::Tableau(ti) => column_len(ti).max(1),
+            _ => 1,
+        };
+        for count in 1..most + 1 {
+            for &to in &POSITIONS {
+                f(Action::Move(Move(count, from, to)));
+            }
+        }
+    }
+}
+
+// The playfields along the solutions of a few deals, so that every stage of a game is included.
+fn sample_playfields() -> Vec<Playfield> {
+    let mut ret = vec![];
+    for deal in 1..6 {
+        let mut pf = make_shuffled_playfield_from_seed(deal);
+        if let SolveResult::Solved(steps) = find_solution(&pf, SearchLimits::default()).result {
+            for step in steps {
+                ret.push(pf.clone());
+                pf = apply_action(pf, step.action).unwrap();
+            }
+        }
+    }
+    ret
+}
+
+// xorshift64, for picking the moves of the random games the same way every time.
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+// The legality check there is for Playfields.
+fn is_legal(playfield: &Playfield, action: Action) -> bool {
+    match action {
+        Action::Move(m) => is_legal_move(playfield, m),
+        Action::FlipDragon(suit) => flip_dragon(playfield.clone(), suit).is_ok(),
+    }
+}
+
+const PLAYOUT_LENGTH: usize = 100;
+
+// A random game with the Playfield functions, with the automatic moves made after each action.
+// Returns the number of actions chosen.
+fn playout_playfield(deal: u64) -> usize {
+    let mut pf = make_shuffled_playfield_from_seed(deal);
+    let mut random = deal;
+    for i in 0..PLAYOUT_LENGTH {
+        pf = auto_play(pf).0;
+        let legal = pf.legal_moves();
+        if legal.is_empty() {
+            return i;
+        }
+        let action = legal[next_random(&mut random) as usize % legal.len()];
+        pf = apply_action(pf, action).unwrap();
+    }
+    PLAYOUT_LENGTH
+}
+
+// The same random game on a CompactBoard, which allocates nothing.
+fn playout_compact(deal: u64) -> usize {
+    let mut board = CompactBoard::from_playfield(&make_shuffled_playfield_from_seed(deal));
+    let mut random = deal;
+    let mut legal = [Action::FlipDragon(Suit::Red); MAX_LEGAL_MOVES];
+    for i in 0..PLAYOUT_LENGTH {
+        while let Some(m) = board.auto_move() {
+            board.apply(Action::Move(m)).unwrap();
+        }
+        let count = board.legal_moves(&mut legal);
+        if count == 0 {
+            return i;
+        }
+        let action = legal[next_random(&mut random) as usize % count];
+        board.apply(action).unwrap();
+    }
+    PLAYOUT_LENGTH
+}
+
+fn bench_legality(c: &mut Criterion) {
+    let playfields = sample_playfields();
+    let boards: Vec<CompactBoard> = playfields.iter().map(CompactBoard::from_playfield).collect();
+    let mut group = c.benchmark_group("legality check");
+    group.bench_function("playfield", |b| b.iter(|| {
+        let mut legal = 0;
+        for pf in &playfields {
+            for_each_candidate(|ti| pf.tableau()[ti].len(), |action| {
+                if is_legal(pf, black_box(action)) {
+                    legal += 1;
+                }
+            });
+        }
+        legal
+    }));
+    group.bench_function("compact", |b| b.iter(|| {
+        let mut legal = 0;
+        for board in &boards {
+            for_each_candidate(|ti| board.column_len(ti), |action| {
+                if board.is_legal(black_box(action)) {
+                    legal += 1;
+                }
+            });
+        }
+        legal
+    }));
+    group.finish();
+}
+
+fn bench_legal_moves(c: &mut Criterion) {
+    let playfields = sample_playfields();
+    let boards: Vec<CompactBoard> = playfields.iter().map(CompactBoard::from_playfield).collect();
+    let mut group = c.benchmark_group("legal moves");
+    group.bench_function("playfield", |b| b.iter(|| {
+        playfields.iter().map(|pf| black_box(pf).legal_moves().len()).sum::<usize>()
+    }));
+    group.bench_function("compact", |b| {
+        let mut moves = [Action::FlipDragon(Suit::Red); MAX_LEGAL_MOVES];
+        b.iter(|| boards.iter().map(|board| black_box(board).legal_moves(&mut moves)).sum::<usize>())
+    });
+    group.finish();
+}
+
+fn bench_apply_undo(c: &mut Criterion) {
+    let playfields = sample_playfields();
+    let legal: Vec<Vec<Action>> = playfields.iter().map(|pf| pf.legal_moves()).collect();
+    let mut boards: Vec<CompactBoard> = playfields.iter().map(CompactBoard::from_playfield).collect();
+    let mut group = c.benchmark_group("apply and undo");
+    // A Playfield can't be changed back, so trying an action means making it on a copy.
+    group.bench_function("playfield", |b| b.iter(|| {
+        for (pf, actions) in playfields.iter().zip(&legal) {
+            for &action in actions {
+                black_box(apply_action(pf.clone(), action).unwrap());
+            }
+        }
+    }));
+    group.bench_function("compact", |b| b.iter(|| {
+        for (board, actions) in boards.iter_mut().zip(&legal) {
+            for &action in actions {
+                let undo = board.apply(action).unwrap();
+                black_box(&board);
+                board.undo(undo);
+            }
+        }
+    }));
+    group.finish();
+}
+
+fn bench_playout(c: &mut Criterion) {
+    assert!((1..11).all(|deal| playout_playfield(deal) == playout_compact(deal)));
+    let mut group = c.benchmark_group("random game");
+    group.bench_function("playfield", |b| b.iter(|| (1..11).map(playout_playfield).sum::<usize>()));
+    group.bench_function("compact", |b| b.iter(|| (1..11).map(playout_compact).sum::<usize>()));
+    group.finish();
+}
+
+criterion_group!(benches, bench_legality, bench_legal_moves, bench_apply_undo, bench_playout);
+criterion_main!(benches);
@@ -0,0 +1,195 @@
+use game_logic::*;
+use solver::*;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Write;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// Solving a range of numbered deals without the UI, for finding out which deals can be won.
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum OutputFormat {
+    /// deal,result,moves,steps,nodes with a header line.
+    Csv,
+    /// One JSON object per deal.
+    JsonLines,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct BatchOptions {
+    /// The deals from @from to @to, inclusive.
+    pub from: u64,
+    pub to: u64,
+    /// Number of deals solved at the same time.
+    pub jobs: usize,
+    /// The budget for each deal.
+    pub limits: SearchLimits,
+    /// Search for the shortest solutions instead of any solutions. This is much slower.
+    pub shortest: bool,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DealReport {
+    pub deal: u64,
+    pub result: SolveResult,
+    pub nodes_expanded: usize,
+}
+
+impl DealReport {
+    // The solvability as written in the output: yes, no or unknown if the solver gave up.
+    fn solvable(&self) -> &'static str {
+        match self.result {
+            SolveResult::Solved(_) => "yes",
+            SolveResult::Unsolvable => "no",
+            SolveResult::GaveUp => "unknown",
+        }
+    }
+
+    // The number of player moves and of all steps in the solution, if one was found.
+    fn solution_length(&self) -> Option<(usize, usize)> {
+        match self.result {
+            SolveResult::Solved(ref steps) => Some((steps.iter().filter(|s| !s.automatic).count(), steps.len())),
+            _ => None,
+        }
+    }
+}
+
+pub fn solve_deal(deal: u64, limits: SearchLimits, shortest: bool) -> DealReport {
+    let playfield = make_shuffled_playfield_from_seed(deal);
+    let report = if shortest { solve(&playfield, limits) } else { find_solution(&playfield, limits) };
+    DealReport { deal, result: report.result, nodes_expanded: report.nodes_expanded }
+}
+
+pub const CSV_HEADER: &str = "deal,solvable,moves,steps,nodes";
+
+pub fn format_report_line(report: &DealReport, format: OutputFormat) -> String {
+    let length = report.solution_length();
+    match format {
+        OutputFormat::Csv => {
+            let (moves, steps) = match length {
+                Some((moves, steps)) => (moves.to_string(), steps.to_string()),
+                None => (String::new(), String::new()),
+            };
+            format!("{},{},{},{},{}", report.deal, report.solvable(), moves, steps, report.nodes_expanded)
+        }
+        OutputFormat::JsonLines => {
+            let (moves, steps) = match length {
+                Some((moves, steps)) => (moves.to_string(), steps.to_string()),
+                None => ("null".to_string(), "null".to_string()),
+            };
+            format!("{{\"deal\":{},\"solvable\":\"{}\",\"moves\":{},\"steps\":{},\"nodes\":{}}}",
+                    report.deal, report.solvable(), moves, steps, report.nodes_expanded)
+        }
+    }
+}
+
+/// Solves all the deals in @options with a pool of worker threads and writes a line for each to
+/// @out, in the order of the deal numbers.
+pub fn run_batch(options: BatchOptions, out: &mut dyn Write) -> io::Result<()> {
+    if options.format == OutputFormat::Csv {
+        writeln!(out, "{}", CSV_HEADER)?;
+    }
+    if options.from > options.to {
+        return Ok(());
+    }
+
+    // The next deal to hand out to a worker, None once they all have been. The range may end at
+    // u64::MAX, so going past its end can't be told by the deal number alone.
+    let next_deal = Arc::new(Mutex::new(Some(options.from)));
+    let (sender, receiver) = mpsc::channel();
+    let workers: Vec<_> = (0..options.jobs.max(1)).map(|_| {
+        let next_deal = next_deal.clone();
+        let sender = sender.clone();
+        thread::spawn(move || loop {
+            let deal = {
+                let mut next = next_deal.lock().unwrap();
+                let deal = match *next {
+                    Some(deal) => deal,
+                    None => break,
+                };
+                *next = if deal < options.to { Some(deal + 1) } else { None };
+                deal
+            };
+            if sender.send(solve_deal(deal, options.limits, options.shortest)).is_err() {
+                break;
+            }
+        })
+    }).collect();
+    drop(sender);
+
+    // The reports come in whatever order the workers finish them.
+    let mut pending = BTreeMap::new();
+    let mut next_to_write = Some(options.from);
+    let mut result = Ok(());
+    for report in receiver {
+        pending.insert(report.deal, report);
+        while let Some(report) = next_to_write.and_then(|deal| pending.remove(&deal)) {
+            result = result.and_then(|_| writeln!(out, "{}", format_report_line(&report, options.format)));
+            next_to_write = report.deal.checked_add(1);
+        }
+        if result.is_err() {
+            // Stop the workers; they notice when sending the next report fails.
+            *next_deal.lock().unwrap() = None;
+            break;
+        }
+    }
+    for worker in workers {
+        let _ = worker.join();
+    }
+    result.and_then(|_| out.flush())
+}
+
+#[test]
+fn test_format_report_line() {
+    let solved = DealReport {
+        deal: 12,
+        result: SolveResult::Solved(vec![
+            Step { action: Action::Move(Move(1, Position::Tableau(0), Position::Pile(0))), automatic: true },
+            Step { action: Action::FlipDragon(Suit::Red), automatic: false },
+        ]),
+        nodes_expanded: 345,
+    };
+    assert_eq!(format_report_line(&solved, OutputFormat::Csv), "12,yes,1,2,345");
+    assert_eq!(format_report_line(&solved, OutputFormat::JsonLines),
+               "{\"deal\":12,\"solvable\":\"yes\",\"moves\":1,\"steps\":2,\"nodes\":345}");
+    let gave_up = DealReport { deal: 13, result: SolveResult::GaveUp, nodes_expanded: 10 };
+    assert_eq!(format_report_line(&gave_up, OutputFormat::Csv), "13,unknown,,,10");
+    assert_eq!(format_report_line(&gave_up, OutputFormat::JsonLines),
+               "{\"deal\":13,\"solvable\":\"unknown\",\"moves\":null,\"steps\":null,\"nodes\":10}");
+}
+
+#[test]
+fn test_run_batch() {
+    let options = BatchOptions {
+        from: 5,
+        to: 9,
+        jobs: 3,
+        limits: SearchLimits { max_nodes: 50, max_time: None },
+        shortest: false,
+        format: OutputFormat::Csv,
+    };
+    let mut out = vec![];
+    run_batch(options, &mut out).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 6);
+    assert_eq!(lines[0], CSV_HEADER);
+    for (i, line) in lines[1..].iter().enumerate() {
+        let expected = format_report_line(&solve_deal(5 + i as u64, options.limits, false), OutputFormat::Csv);
+        assert_eq!(*line, expected);
+    }
+
+    // The range can go up to the very last deal number.
+    let options = BatchOptions { from: u64::MAX - 2, to: u64::MAX, format: OutputFormat::JsonLines, ..options };
+    let mut out = vec![];
+    run_batch(options, &mut out).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 3);
+    for (i, line) in lines.iter().enumerate() {
+        assert!(line.starts_with(&format!("{{\"deal\":{},", u64::MAX - 2 + i as u64)));
+    }
+}
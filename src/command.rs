@@ -0,0 +1,104 @@
+use game_logic::*;
+use savefile::*;
+
+/// Everything that can be typed on the command line at the bottom of the screen, which ":" opens
+/// during a game.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Command {
+    Action(Action),
+    /// Move the flower from whichever tableau column has it on top to the flower spot.
+    /// Resolving the column needs the playfield, so that's left to the caller.
+    PlaceFlower,
+    /// Run the solver from the current position and show the solution.
+    Solve,
+    /// Suggest a move that keeps the game winnable.
+    Hint,
+    Undo,
+    Redo,
+    /// Jump to the given step in the game's history.
+    Goto(usize),
+    /// Save the game into the given file.
+    Save(String),
+    /// Save just the current board into the given file.
+    Export(String),
+    /// Load a game or a board from the given file.
+    Load(String),
+    Help,
+    Quit,
+}
+
+pub const HELP_TEXT: &str = "\
+Commands:
+  t3 f1         move the top card of tableau column 3 to free cell 1
+  3 t2 t5       move the top 3 cards of tableau column 2 onto column 5
+  dragon red    flip the four exposed red dragons (also green, black)
+  flower        move the flower to the flower spot
+  solve         search for the shortest way to win from here
+  hint          suggest a move that keeps the game winnable
+  u, undo       take back the last move
+  r, redo       redo the last move taken back
+  goto 12       jump to step 12 of the game (0 is the start)
+  save FILE     save the game
+  export FILE   save the current board, e.g. for a bug report
+  load FILE     load a saved game or board
+  help          show this text
+  quit          quit the game
+Positions: f1-f3 (free cells), p1-p3 (piles), t1-t8 (tableau), flower";
+
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let lowercase = line.to_lowercase();
+    let words: Vec<&str> = lowercase.split_whitespace().collect();
+    // File names are case sensitive.
+    let original_words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        [] => Err("Empty command, type 'help' for a list of commands".to_string()),
+        ["q"] | ["quit"] | ["exit"] => Ok(Command::Quit),
+        ["h"] | ["help"] | ["?"] => Ok(Command::Help),
+        ["flower"] => Ok(Command::PlaceFlower),
+        ["solve"] => Ok(Command::Solve),
+        ["hint"] => Ok(Command::Hint),
+        ["u"] | ["undo"] => Ok(Command::Undo),
+        ["r"] | ["redo"] => Ok(Command::Redo),
+        ["goto", step] => match step.parse::<usize>() {
+            Ok(n) => Ok(Command::Goto(n)),
+            Err(_) => Err(format!("Invalid step number '{}'", step)),
+        },
+        ["save", _] => Ok(Command::Save(original_words[1].to_string())),
+        ["export", _] => Ok(Command::Export(original_words[1].to_string())),
+        ["load", _] => Ok(Command::Load(original_words[1].to_string())),
+        ["dragon", _] | ["d", _] | [_, _] | [_, _, _] => parse_action(line).map(Command::Action),
+        _ => Err(format!("Unknown command '{}', type 'help' for a list of commands", line.trim())),
+    }
+}
+
+#[test]
+fn test_parse_command() {
+    assert_eq!(parse_command("t3 f1"),
+               Ok(Command::Action(Action::Move(Move(1, Position::Tableau(2), Position::FreeCell(0))))));
+    assert_eq!(parse_command(" 3  T2 t5 "),
+               Ok(Command::Action(Action::Move(Move(3, Position::Tableau(1), Position::Tableau(4))))));
+    assert_eq!(parse_command("f2 p3"),
+               Ok(Command::Action(Action::Move(Move(1, Position::FreeCell(1), Position::Pile(2))))));
+    assert_eq!(parse_command("t1 flower"),
+               Ok(Command::Action(Action::Move(Move(1, Position::Tableau(0), Position::Flower)))));
+    assert_eq!(parse_command("dragon red"), Ok(Command::Action(Action::FlipDragon(Suit::Red))));
+    assert_eq!(parse_command("d b"), Ok(Command::Action(Action::FlipDragon(Suit::Black))));
+    assert_eq!(parse_command("flower"), Ok(Command::PlaceFlower));
+    assert_eq!(parse_command("quit"), Ok(Command::Quit));
+    assert_eq!(parse_command("hint"), Ok(Command::Hint));
+    assert_eq!(parse_command("u"), Ok(Command::Undo));
+    assert_eq!(parse_command("redo"), Ok(Command::Redo));
+    assert_eq!(parse_command("goto 0"), Ok(Command::Goto(0)));
+    assert_eq!(parse_command("save My.Game"), Ok(Command::Save("My.Game".to_string())));
+    assert_eq!(parse_command("LOAD x"), Ok(Command::Load("x".to_string())));
+
+    assert!(parse_command("").is_err());
+    assert!(parse_command("t9 f1").is_err());
+    assert!(parse_command("t0 f1").is_err());
+    assert!(parse_command("f4 t1").is_err());
+    assert!(parse_command("0 t1 t2").is_err());
+    assert!(parse_command("x1 t2").is_err());
+    assert!(parse_command("dragon blue").is_err());
+    assert!(parse_command("t1 t2 t3 t4").is_err());
+    assert!(parse_command("goto -1").is_err());
+}
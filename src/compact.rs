@@ -0,0 +1,586 @@
+use game_logic::*;
+
+// A playfield in fixed-size arrays with every card packed into a byte, for searches and playouts
+// that go through millions of states. A CompactBoard is Copy, doesn't allocate, and actions are
+// applied to it in place and can be undone again, so a search can walk the game tree with a
+// single board.
+//
+// The rules are the same as in game_logic and so are the errors for breaking them; the tests check
+// that the two agree.
+
+const NO_CARD: u8 = 0;
+const DRAGON: u8 = 30;
+const FLOWER: u8 = 33;
+const FLIPPED: u8 = 34;
+
+/// Packs @card into a byte: the numbered cards are 1-9, 11-19 and 21-29 for the red, green and
+/// black suits, the dragons are 30-32 in the same order and the flower is 33. 0 is no card.
+pub fn pack_card(card: Card) -> u8 {
+    match card {
+        Card::Number(suit, number) => (suit.to_index() * 10 + number) as u8,
+        Card::Dragon(suit) => DRAGON + suit.to_index() as u8,
+        Card::Flower => FLOWER,
+    }
+}
+
+pub fn unpack_card(card: u8) -> Card {
+    match card {
+        DRAGON..=32 => Card::Dragon(Suit::from_index((card - DRAGON) as usize)),
+        FLOWER => Card::Flower,
+        _ => {
+            assert!(is_number(card), "{} is not a packed card", card);
+            Card::Number(Suit::from_index(card as usize / 10), card as usize % 10)
+        }
+    }
+}
+
+/// Packs a free cell like pack_card() does cards, with a free cell as 0 and the flipped dragons
+/// as 34-36.
+pub fn pack_freecell(fc: FreeCell) -> u8 {
+    match fc {
+        FreeCell::Free => NO_CARD,
+        FreeCell::InUse(card) => pack_card(card),
+        FreeCell::Flipped(suit) => FLIPPED + suit.to_index() as u8,
+    }
+}
+
+fn unpack_freecell(fc: u8) -> FreeCell {
+    match fc {
+        NO_CARD => FreeCell::Free,
+        FLIPPED..=36 => FreeCell::Flipped(Suit::from_index((fc - FLIPPED) as usize)),
+        _ => FreeCell::InUse(unpack_card(fc)),
+    }
+}
+
+fn is_number(card: u8) -> bool {
+    card < DRAGON && !card.is_multiple_of(10)
+}
+
+// can_place_on_top() for packed cards.
+fn goes_on_top(card: u8, top: u8) -> bool {
+    is_number(card) && is_number(top) && card / 10 != top / 10 && top % 10 == card % 10 + 1
+}
+
+/// The most actions CompactBoard::legal_moves() can find: the three dragon flips, and each of the
+/// 40 cards being the bottom card of a move to one of the 13 destinations that are tried.
+pub const MAX_LEGAL_MOVES: usize = 3 + 40 * 13;
+
+/// What it takes to undo an action made with CompactBoard::apply().
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Undo {
+    Move(Move),
+    /// @sources has a bit set for each free cell (bits 0-2) and tableau column (bits 3-10) a
+    /// dragon was taken from, and @cell is the free cell the dragons were flipped into.
+    FlipDragon { suit: Suit, sources: u16, cell: u8 },
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct CompactBoard {
+    freecells: [u8; 3],
+    flipped_suits: [bool; 3],
+    flower: u8,
+    /// Topmost card of each pile.
+    piles: [u8; 3],
+    /// The cards above the height of a column are always NO_CARD, so equal boards compare equal.
+    tableau: [[u8; MAX_COLUMN_HEIGHT]; 8],
+    heights: [u8; 8],
+}
+
+impl CompactBoard {
+    /// The tableau columns of @playfield must be at most MAX_COLUMN_HEIGHT cards tall, which they
+    /// are on any valid playfield.
+    pub fn from_playfield(playfield: &Playfield) -> CompactBoard {
+        let mut board = CompactBoard {
+            freecells: [NO_CARD; 3],
+            flipped_suits: playfield.flipped_suits,
+            flower: playfield.flower.map_or(NO_CARD, pack_card),
+            piles: [NO_CARD; 3],
+            tableau: [[NO_CARD; MAX_COLUMN_HEIGHT]; 8],
+            heights: [0; 8],
+        };
+        for (fi, &fc) in playfield.freecells.iter().enumerate() {
+            board.freecells[fi] = pack_freecell(fc);
+        }
+        for (pi, &pile) in playfield.piles.iter().enumerate() {
+            board.piles[pi] = pile.map_or(NO_CARD, pack_card);
+        }
+        for (ti, col) in playfield.tableau.iter().enumerate() {
+            assert!(col.len() <= MAX_COLUMN_HEIGHT, "Column {} is too tall", ti + 1);
+            for (i, &card) in col.iter().enumerate() {
+                board.tableau[ti][i] = pack_card(card);
+            }
+            board.heights[ti] = col.len() as u8;
+        }
+        board
+    }
+
+    pub fn to_playfield(&self) -> Playfield {
+        let mut pf = Playfield::empty();
+        for fi in 0..self.freecells.len() {
+            pf.freecells[fi] = unpack_freecell(self.freecells[fi]);
+        }
+        pf.flipped_suits = self.flipped_suits;
+        pf.flower = if self.flower == NO_CARD { None } else { Some(unpack_card(self.flower)) };
+        for pi in 0..self.piles.len() {
+            pf.piles[pi] = if self.piles[pi] == NO_CARD { None } else { Some(unpack_card(self.piles[pi])) };
+        }
+        for ti in 0..self.tableau.len() {
+            pf.tableau[ti] = self.column(ti).iter().map(|&card| unpack_card(card)).collect();
+        }
+        pf
+    }
+
+    fn column(&self, ti: usize) -> &[u8] {
+        &self.tableau[ti][..self.heights[ti] as usize]
+    }
+
+    /// Number of cards in the tableau column @ti, the most that a move from there can pick up.
+    pub fn column_len(&self, ti: usize) -> usize {
+        self.heights[ti] as usize
+    }
+
+    pub fn is_won(&self) -> bool {
+        self.piles.iter().all(|&p| is_number(p) && p % 10 == 9) &&
+            self.flipped_suits.iter().all(|&flipped| flipped) &&
+            self.flower == FLOWER
+    }
+
+    // Like cards_to_pick_up(), but only the bottom card of the ones picked up is returned.
+    fn check_pick_up(&self, count: usize, from: Position) -> Result<u8, MoveError> {
+        assert!(count > 0);
+        match from {
+            Position::Flower => Err(MoveError::CannotPickFromFlower),
+            Position::Pile(_) => Err(MoveError::CannotPickFromPile(from)),
+            Position::FreeCell(fi) => match self.freecells[fi] {
+                NO_CARD => Err(MoveError::SourceEmpty(from)),
+                fc if fc >= FLIPPED => Err(MoveError::DragonsFlipped(from)),
+                card if count == 1 => Ok(card),
+                _ => Err(MoveError::NotEnoughCards { position: from, available: 1 }),
+            },
+            Position::Tableau(ti) => {
+                let col = self.column(ti);
+                if col.is_empty() {
+                    return Err(MoveError::SourceEmpty(from));
+                }
+                if count > col.len() {
+                    return Err(MoveError::NotEnoughCards { position: from, available: col.len() });
+                }
+                let run = &col[col.len() - count..];
+                if run.windows(2).all(|w| goes_on_top(w[1], w[0])) {
+                    Ok(run[0])
+                } else {
+                    Err(MoveError::NotARun { position: from, count })
+                }
+            }
+        }
+    }
+
+    // Like check_place_cards(), for @count cards with @card at the bottom.
+    fn check_place(&self, count: usize, card: u8, to: Position) -> Result<(), MoveError> {
+        if let Position::Tableau(ti) = to {
+            return match self.column(ti).last() {
+                None => Ok(()),
+                Some(&top) if goes_on_top(card, top) => Ok(()),
+                Some(&top) => Err(MoveError::WrongSuitOrder { card: unpack_card(card), top: unpack_card(top), position: to }),
+            };
+        }
+        if count != 1 {
+            return Err(MoveError::SingleCardOnly(to));
+        }
+        match to {
+            Position::FreeCell(fi) if self.freecells[fi] == NO_CARD => Ok(()),
+            Position::FreeCell(_) => Err(MoveError::FreeCellOccupied(to)),
+            Position::Flower if card == FLOWER => Ok(()),
+            Position::Flower => Err(MoveError::NotAFlower(unpack_card(card))),
+            Position::Pile(pi) if is_number(card) => match self.piles[pi] {
+                NO_CARD if card % 10 != 1 => Err(MoveError::PileNeedsOne(to)),
+                NO_CARD if self.piles.iter().any(|&p| p != NO_CARD && p / 10 == card / 10) =>
+                    Err(MoveError::SuitHasPile(Suit::from_index(card as usize / 10))),
+                NO_CARD => Ok(()),
+                top if top / 10 == card / 10 && card == top + 1 => Ok(()),
+                top => Err(MoveError::WrongSuitOrder { card: unpack_card(card), top: unpack_card(top), position: to }),
+            },
+            Position::Pile(_) => Err(MoveError::NotANumber(unpack_card(card))),
+            Position::Tableau(_) => unreachable!(),
+        }
+    }
+
+    // Like flip_dragon(), returning the sources and the free cell of Undo::FlipDragon.
+    fn check_flip(&self, suit: Suit) -> Result<(u16, usize), MoveError> {
+        if self.flipped_suits[suit.to_index()] {
+            return Err(MoveError::SuitAlreadyFlipped(suit));
+        }
+        let dragon = DRAGON + suit.to_index() as u8;
+        let mut sources = 0u16;
+        let mut exposed = 0;
+        let mut cell = None;
+        for (fi, &fc) in self.freecells.iter().enumerate() {
+            if fc == dragon {
+                sources |= 1 << fi;
+                exposed += 1;
+                cell = Some(fi);
+            } else if fc == NO_CARD {
+                cell = Some(fi);
+            }
+        }
+        for ti in 0..self.tableau.len() {
+            if self.column(ti).last() == Some(&dragon) {
+                sources |= 1 << (3 + ti);
+                exposed += 1;
+            }
+        }
+        if exposed != 4 {
+            return Err(MoveError::DragonsNotExposed { suit, exposed });
+        }
+        cell.map(|cell| (sources, cell)).ok_or(MoveError::NoFreeCellForDragons(suit))
+    }
+
+    /// Checks whether @action is legal, returning the same reason as apply_action() if it isn't.
+    /// Nothing is allocated.
+    pub fn check(&self, action: Action) -> Result<(), MoveError> {
+        match action {
+            Action::Move(Move(count, from, to)) => {
+                if from == to {
+                    return Err(MoveError::SamePosition);
+                }
+                let card = self.check_pick_up(count, from)?;
+                self.check_place(count, card, to)
+            }
+            Action::FlipDragon(suit) => self.check_flip(suit).map(|_| ()),
+        }
+    }
+
+    pub fn is_legal(&self, action: Action) -> bool {
+        self.check(action).is_ok()
+    }
+
+    // Moves the top @count cards of @from onto @to, without checking any rules.
+    fn transfer(&mut self, count: usize, from: Position, to: Position) {
+        let mut cards = [NO_CARD; MAX_COLUMN_HEIGHT];
+        match from {
+            Position::FreeCell(fi) => {
+                cards[0] = self.freecells[fi];
+                self.freecells[fi] = NO_CARD;
+            }
+            Position::Flower => {
+                cards[0] = self.flower;
+                self.flower = NO_CARD;
+            }
+            Position::Pile(pi) => {
+                // The pile goes back to the card one lower, or to nothing if that was a 1.
+                cards[0] = self.piles[pi];
+                self.piles[pi] = if cards[0] % 10 == 1 { NO_CARD } else { cards[0] - 1 };
+            }
+            Position::Tableau(ti) => {
+                let height = self.heights[ti] as usize;
+                let start = height - count;
+                cards[..count].copy_from_slice(&self.tableau[ti][start..height]);
+                for slot in &mut self.tableau[ti][start..height] {
+                    *slot = NO_CARD;
+                }
+                self.heights[ti] = start as u8;
+            }
+        }
+        match to {
+            Position::FreeCell(fi) => self.freecells[fi] = cards[0],
+            Position::Flower => self.flower = cards[0],
+            Position::Pile(pi) => self.piles[pi] = cards[0],
+            Position::Tableau(ti) => {
+                let height = self.heights[ti] as usize;
+                self.tableau[ti][height..height + count].copy_from_slice(&cards[..count]);
+                self.heights[ti] += count as u8;
+            }
+        }
+    }
+
+    // Adds the moves of @count cards with @card at the bottom from @from to each of @destinations
+    // that's allowed and not pointless into @moves, from index @len on. Returns the new length.
+    fn push_moves(&self, moves: &mut [Action; MAX_LEGAL_MOVES], mut len: usize, count: usize, card: u8,
+                  from: Position, destinations: &[Position]) -> usize {
+        for &to in destinations {
+            let pointless = match (from, to) {
+                (Position::FreeCell(_), Position::FreeCell(_)) => true,
+                (Position::Tableau(ti), Position::Tableau(di)) =>
+                    ti == di || (self.heights[di] == 0 && count == self.heights[ti] as usize),
+                _ => false,
+            };
+            if !pointless && self.check_place(count, card, to).is_ok() {
+                moves[len] = Action::Move(Move(count, from, to));
+                len += 1;
+            }
+        }
+        len
+    }
+
+    /// Puts the same actions as Playfield::legal_moves() into @moves, in the same order, and
+    /// returns how many there are. Nothing is allocated.
+    pub fn legal_moves(&self, moves: &mut [Action; MAX_LEGAL_MOVES]) -> usize {
+        let mut len = 0;
+        for i in 0..self.flipped_suits.len() {
+            let suit = Suit::from_index(i);
+            if self.check_flip(suit).is_ok() {
+                moves[len] = Action::FlipDragon(suit);
+                len += 1;
+            }
+        }
+
+        // Empty free cells, piles and columns are interchangeable, so only the first one is tried.
+        let mut destinations = [Position::Flower; 13];
+        let mut destination_count = 1;
+        let first_empty_pile = self.piles.iter().position(|&p| p == NO_CARD);
+        for pi in 0..self.piles.len() {
+            if self.piles[pi] != NO_CARD || Some(pi) == first_empty_pile {
+                destinations[destination_count] = Position::Pile(pi);
+                destination_count += 1;
+            }
+        }
+        if let Some(fi) = self.freecells.iter().position(|&fc| fc == NO_CARD) {
+            destinations[destination_count] = Position::FreeCell(fi);
+            destination_count += 1;
+        }
+        let first_empty_column = self.heights.iter().position(|&h| h == 0);
+        for ti in 0..self.tableau.len() {
+            if self.heights[ti] != 0 || Some(ti) == first_empty_column {
+                destinations[destination_count] = Position::Tableau(ti);
+                destination_count += 1;
+            }
+        }
+        let destinations = &destinations[..destination_count];
+
+        for fi in 0..self.freecells.len() {
+            let card = self.freecells[fi];
+            if card != NO_CARD && card < FLIPPED {
+                len = self.push_moves(moves, len, 1, card, Position::FreeCell(fi), destinations);
+            }
+        }
+        for ti in 0..self.tableau.len() {
+            let col = self.column(ti);
+            for count in 1..col.len() + 1 {
+                let card = col[col.len() - count];
+                if count > 1 && !goes_on_top(col[col.len() - count + 1], card) {
+                    break;
+                }
+                len = self.push_moves(moves, len, count, card, Position::Tableau(ti), destinations);
+            }
+        }
+        len
+    }
+
+    // The value of the topmost card on the pile of @suit, or 0 if there's no such pile yet.
+    fn pile_value(&self, suit: u8) -> u8 {
+        self.piles.iter().filter(|&&p| is_number(p) && p / 10 == suit).map(|&p| p % 10).max().unwrap_or(0)
+    }
+
+    /// The same automatic move as auto_move() would make on the playfield, if any. Making all of
+    /// them until there are none left is what auto_play() does.
+    pub fn auto_move(&self) -> Option<Move> {
+        let freecell_cards = (0..self.freecells.len())
+            .filter(|&fi| self.freecells[fi] != NO_CARD && self.freecells[fi] < FLIPPED)
+            .map(|fi| (Position::FreeCell(fi), self.freecells[fi]));
+        let tableau_cards = (0..self.tableau.len())
+            .filter_map(|ti| self.column(ti).last().map(|&card| (Position::Tableau(ti), card)));
+
+        for (from, card) in freecell_cards.chain(tableau_cards) {
+            if card == FLOWER {
+                return Some(Move(1, from, Position::Flower));
+            }
+            if !is_number(card) {
+                continue;
+            }
+            let (suit, number) = (card / 10, card % 10);
+            let safe = number <= 2 || (0..3).all(|other| other == suit || self.pile_value(other) + 1 >= number);
+            if !safe {
+                continue;
+            }
+            let target = if number == 1 {
+                self.piles.iter().position(|&p| p == NO_CARD)
+            } else {
+                self.piles.iter().position(|&p| p == card - 1)
+            };
+            if let Some(pi) = target {
+                return Some(Move(1, from, Position::Pile(pi)));
+            }
+        }
+        None
+    }
+
+    /// Makes @action on the board, if it's legal. The returned Undo takes the board back to how
+    /// it was with undo(). Nothing is allocated.
+    pub fn apply(&mut self, action: Action) -> Result<Undo, MoveError> {
+        match action {
+            Action::Move(m) => {
+                self.check(action)?;
+                let Move(count, from, to) = m;
+                self.transfer(count, from, to);
+                Ok(Undo::Move(m))
+            }
+            Action::FlipDragon(suit) => {
+                let (sources, cell) = self.check_flip(suit)?;
+                for fi in 0..self.freecells.len() {
+                    if sources & (1 << fi) != 0 {
+                        self.freecells[fi] = NO_CARD;
+                    }
+                }
+                for ti in 0..self.tableau.len() {
+                    if sources & (1 << (3 + ti)) != 0 {
+                        self.heights[ti] -= 1;
+                        self.tableau[ti][self.heights[ti] as usize] = NO_CARD;
+                    }
+                }
+                self.freecells[cell] = FLIPPED + suit.to_index() as u8;
+                self.flipped_suits[suit.to_index()] = true;
+                Ok(Undo::FlipDragon { suit, sources, cell: cell as u8 })
+            }
+        }
+    }
+
+    /// Takes back an action made with apply(). The actions must be undone in the reverse order
+    /// they were made in.
+    pub fn undo(&mut self, undo: Undo) {
+        match undo {
+            Undo::Move(Move(count, from, to)) => self.transfer(count, to, from),
+            Undo::FlipDragon { suit, sources, cell } => {
+                let dragon = DRAGON + suit.to_index() as u8;
+                self.freecells[cell as usize] = NO_CARD;
+                for fi in 0..self.freecells.len() {
+                    if sources & (1 << fi) != 0 {
+                        self.freecells[fi] = dragon;
+                    }
+                }
+                for ti in 0..self.tableau.len() {
+                    if sources & (1 << (3 + ti)) != 0 {
+                        self.tableau[ti][self.heights[ti] as usize] = dragon;
+                        self.heights[ti] += 1;
+                    }
+                }
+                self.flipped_suits[suit.to_index()] = false;
+            }
+        }
+    }
+}
+
+// Every action that could be tried on a board with @playfield's column heights, legal or not.
+#[cfg(test)]
+fn candidate_actions(playfield: &Playfield) -> Vec<Action> {
+    let mut positions: Vec<Position> = (0..3).map(Position::FreeCell).collect();
+    positions.push(Position::Flower);
+    positions.extend((0..3).map(Position::Pile));
+    positions.extend((0..8).map(Position::Tableau));
+    let mut actions: Vec<Action> = (0..3).map(|i| Action::FlipDragon(Suit::from_index(i))).collect();
+    for &from in &positions {
+        let most = match from {
+            Position::Tableau(ti) => playfield.tableau[ti].len().max(1) + 1,
+            _ => 2,
+        };
+        for count in 1..most + 1 {
+            actions.extend(positions.iter().map(|&to| Action::Move(Move(count, from, to))));
+        }
+    }
+    actions
+}
+
+#[test]
+fn test_pack_card() {
+    for card in make_deck() {
+        assert_eq!(unpack_card(pack_card(card)), card);
+    }
+    assert_eq!(pack_card(Card::Number(Suit::Green, 7)), 17);
+    for fc in [FreeCell::Free, FreeCell::InUse(Card::Flower), FreeCell::Flipped(Suit::Black)].iter() {
+        assert_eq!(unpack_freecell(pack_freecell(*fc)), *fc);
+    }
+}
+
+#[test]
+fn test_flip_and_undo() {
+    let pf = Playfield::empty()
+        .with_freecell_cards(vec![Card::Dragon(Suit::Red), Card::Number(Suit::Black, 4)], FreeCell::Free)
+        .with_tableau_column(0, vec![Card::Number(Suit::Green, 3), Card::Dragon(Suit::Red)])
+        .with_tableau_column(2, vec![Card::Dragon(Suit::Red)])
+        .with_tableau_column(7, vec![Card::Dragon(Suit::Red), Card::Dragon(Suit::Red)]);
+    let mut board = CompactBoard::from_playfield(&pf);
+    let undo = board.apply(Action::FlipDragon(Suit::Red)).unwrap();
+    assert_eq!(undo, Undo::FlipDragon { suit: Suit::Red, sources: 1 | 1 << 3 | 1 << 5 | 1 << 10, cell: 2 });
+    assert_eq!(board.to_playfield(), flip_dragon(pf.clone(), Suit::Red).unwrap());
+    assert_eq!(board.check(Action::FlipDragon(Suit::Red)), Err(MoveError::SuitAlreadyFlipped(Suit::Red)));
+    board.undo(undo);
+    assert_eq!(board, CompactBoard::from_playfield(&pf));
+
+    // An illegal action changes nothing
+    let m = Move(2, Position::Tableau(7), Position::Tableau(4));
+    assert_eq!(board.apply(Action::Move(m)), Err(MoveError::NotARun { position: Position::Tableau(7), count: 2 }));
+    assert_eq!(board, CompactBoard::from_playfield(&pf));
+}
+
+// Plays through the solutions of a few deals, checking every action that could be tried along the
+// way against apply_action() and that undoing it brings the board back, and the legal and
+// automatic moves against those of the playfield.
+#[test]
+fn test_agrees_with_game_logic() {
+    for deal in 1..4 {
+        let mut pf = make_shuffled_playfield_from_seed(deal);
+        let steps = match ::solver::find_solution(&pf, ::solver::SearchLimits::default()).result {
+            ::solver::SolveResult::Solved(steps) => steps,
+            r => panic!("unexpected result {:?}", r),
+        };
+        let mut moves = [Action::FlipDragon(Suit::Red); MAX_LEGAL_MOVES];
+        for step in steps {
+            let mut board = CompactBoard::from_playfield(&pf);
+            assert_eq!(board.to_playfield(), pf);
+            let len = board.legal_moves(&mut moves);
+            assert_eq!(&moves[..len], &pf.legal_moves()[..]);
+            assert_eq!(board.auto_move(), auto_move(&pf));
+            for action in candidate_actions(&pf) {
+                let expected = apply_action(pf.clone(), action);
+                assert_eq!(board.check(action), expected.as_ref().map(|_| ()).map_err(|&e| e), "{:?}", action);
+                let before = board;
+                match (board.apply(action), expected) {
+                    (Ok(undo), Ok(after)) => {
+                        assert_eq!(board.to_playfield(), after, "{:?}", action);
+                        assert_eq!(board, CompactBoard::from_playfield(&after));
+                        board.undo(undo);
+                    }
+                    (Err(error), Err(expected)) => assert_eq!(error, expected),
+                    (result, expected) => panic!("{:?}: {:?} vs {:?}", action, result, expected),
+                }
+                assert_eq!(board, before);
+            }
+            assert!(board.apply(step.action).is_ok());
+            pf = apply_action(pf, step.action).unwrap();
+            assert_eq!(board.to_playfield(), pf);
+        }
+        assert!(CompactBoard::from_playfield(&pf).is_won());
+    }
+}
+
+// Random games of up to 200 moves, with the legal and automatic moves of the board checked against
+// the playfield's after each one. These reach other positions than the solutions do.
+#[test]
+fn test_random_games_agree_with_game_logic() {
+    let mut moves = [Action::FlipDragon(Suit::Red); MAX_LEGAL_MOVES];
+    for deal in 1..21 {
+        let mut pf = make_shuffled_playfield_from_seed(deal);
+        let mut board = CompactBoard::from_playfield(&pf);
+        let mut random = deal;
+        for _ in 0..200 {
+            while let Some(m) = board.auto_move() {
+                assert_eq!(auto_move(&pf), Some(m));
+                board.apply(Action::Move(m)).unwrap();
+                pf = apply_move(pf, m).unwrap();
+            }
+            assert_eq!(auto_move(&pf), None);
+            let len = board.legal_moves(&mut moves);
+            assert_eq!(&moves[..len], &pf.legal_moves()[..]);
+            if len == 0 || board.is_won() {
+                break;
+            }
+            // xorshift64
+            random ^= random << 13;
+            random ^= random >> 7;
+            random ^= random << 17;
+            let action = moves[random as usize % len];
+            board.apply(action).unwrap();
+            pf = apply_action(pf, action).unwrap();
+            assert_eq!(board.to_playfield(), pf);
+        }
+    }
+}
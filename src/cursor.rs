@@ -0,0 +1,142 @@
+use game_logic::*;
+
+// Selecting and moving cards with a cursor. The cursor moves over the free cells, the flower spot,
+// the piles and the tableau columns.
+// A selection is made by picking up cards at the cursor, and the move happens when they're
+// placed at another position.
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+// The top row is drawn as the free cells, the dragon buttons, the flower spot and the piles. The
+// free cells are right above columns t1-t3, the flower spot above t5 and the piles above t6-t8.
+const TOP_ROW: [Position; 7] = [
+    Position::FreeCell(0), Position::FreeCell(1), Position::FreeCell(2), Position::Flower,
+    Position::Pile(0), Position::Pile(1), Position::Pile(2),
+];
+
+pub fn move_cursor(position: Position, direction: Direction) -> Position {
+    match (position, direction) {
+        (Position::Tableau(ti), Direction::Left) => Position::Tableau(ti.saturating_sub(1)),
+        (Position::Tableau(ti), Direction::Right) => Position::Tableau((ti + 1).min(7)),
+        (Position::Tableau(ti), Direction::Up) => match ti {
+            0..=3 => Position::FreeCell(ti.min(2)),
+            4 => Position::Flower,
+            _ => Position::Pile(ti - 5),
+        },
+        (Position::Tableau(_), Direction::Down) => position,
+        (_, Direction::Down) => match position {
+            Position::FreeCell(fi) => Position::Tableau(fi),
+            Position::Pile(pi) => Position::Tableau(pi + 5),
+            _ => Position::Tableau(4),
+        },
+        (_, Direction::Up) => position,
+        (_, _) => {
+            let index = TOP_ROW.iter().position(|&p| p == position).unwrap_or(0);
+            if direction == Direction::Left {
+                TOP_ROW[index.saturating_sub(1)]
+            } else {
+                TOP_ROW[(index + 1).min(TOP_ROW.len() - 1)]
+            }
+        }
+    }
+}
+
+/// The number of cards picked up when selecting @position: the longest run that can be moved
+/// from there, or 0 if there's nothing to pick up.
+pub fn longest_run(playfield: &Playfield, position: Position) -> usize {
+    let mut count = 0;
+    while cards_to_pick_up(playfield, count + 1, position).is_ok() {
+        count += 1;
+    }
+    count
+}
+
+/// The move for placing the @count selected cards from @from at @to. On a tableau column, only as
+/// many of the selected cards are moved as fit there; elsewhere only one card fits at a time.
+/// The move is returned even if it's not legal, so the caller can explain why it isn't.
+pub fn drop_move(playfield: &Playfield, from: Position, count: usize, to: Position) -> Move {
+    match to {
+        Position::Tableau(_) => {
+            let fits = (1..count + 1).rev().find(|&n| match cards_to_pick_up(playfield, n, from) {
+                Ok(cards) => can_place_cards(playfield, cards, to),
+                Err(_) => false,
+            });
+            Move(fits.unwrap_or(count), from, to)
+        }
+        _ => Move(1, from, to),
+    }
+}
+
+/// Where a double-clicked card goes: to the flower spot or a pile if it fits there, otherwise to a
+/// free cell.
+pub fn quick_move(playfield: &Playfield, from: Position) -> Option<Move> {
+    let cards = cards_to_pick_up(playfield, 1, from).ok()?;
+    let freecells = match from {
+        Position::FreeCell(_) => 0,
+        _ => playfield.freecells().len(),
+    };
+    let destinations = Some(Position::Flower).into_iter()
+        .chain((0..playfield.piles().len()).map(Position::Pile))
+        .chain((0..freecells).map(Position::FreeCell));
+    destinations.filter(|&to| can_place_cards(playfield, cards, to)).map(|to| Move(1, from, to)).next()
+}
+
+#[test]
+fn test_move_cursor() {
+    assert_eq!(move_cursor(Position::Tableau(0), Direction::Left), Position::Tableau(0));
+    assert_eq!(move_cursor(Position::Tableau(0), Direction::Right), Position::Tableau(1));
+    assert_eq!(move_cursor(Position::Tableau(7), Direction::Right), Position::Tableau(7));
+    assert_eq!(move_cursor(Position::Tableau(1), Direction::Up), Position::FreeCell(1));
+    assert_eq!(move_cursor(Position::Tableau(3), Direction::Up), Position::FreeCell(2));
+    assert_eq!(move_cursor(Position::Tableau(4), Direction::Up), Position::Flower);
+    assert_eq!(move_cursor(Position::Tableau(5), Direction::Up), Position::Pile(0));
+    assert_eq!(move_cursor(Position::Tableau(7), Direction::Up), Position::Pile(2));
+    assert_eq!(move_cursor(Position::FreeCell(2), Direction::Right), Position::Flower);
+    assert_eq!(move_cursor(Position::Flower, Direction::Right), Position::Pile(0));
+    assert_eq!(move_cursor(Position::Flower, Direction::Down), Position::Tableau(4));
+    assert_eq!(move_cursor(Position::Pile(2), Direction::Right), Position::Pile(2));
+    assert_eq!(move_cursor(Position::Pile(1), Direction::Down), Position::Tableau(6));
+    assert_eq!(move_cursor(Position::FreeCell(0), Direction::Up), Position::FreeCell(0));
+}
+
+#[test]
+fn test_drop_move() {
+    let pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Dragon(Suit::Red), Card::Number(Suit::Red, 7),
+                                     Card::Number(Suit::Black, 6), Card::Number(Suit::Green, 5)])
+        .with_tableau_column(1, vec![Card::Number(Suit::Green, 7)])
+        .with_tableau_column(2, vec![Card::Number(Suit::Green, 9)]);
+    assert_eq!(longest_run(&pf, Position::Tableau(0)), 3);
+    assert_eq!(longest_run(&pf, Position::Tableau(3)), 0);
+    assert_eq!(longest_run(&pf, Position::FreeCell(0)), 0);
+
+    let from = Position::Tableau(0);
+    assert_eq!(drop_move(&pf, from, 3, Position::Tableau(1)), Move(2, from, Position::Tableau(1)));
+    assert_eq!(drop_move(&pf, from, 3, Position::Tableau(3)), Move(3, from, Position::Tableau(3)));
+    assert_eq!(drop_move(&pf, from, 3, Position::FreeCell(0)), Move(1, from, Position::FreeCell(0)));
+    // Nothing fits: the whole selection is tried, so the caller can tell why it doesn't work
+    assert_eq!(drop_move(&pf, from, 2, Position::Tableau(2)), Move(2, from, Position::Tableau(2)));
+}
+
+#[test]
+fn test_quick_move() {
+    let pf = Playfield::empty()
+        .with_freecell_cards(vec![Card::Number(Suit::Green, 3)], FreeCell::Free)
+        .with_piles([Some(Card::Number(Suit::Red, 9)), Some(Card::Number(Suit::Black, 1)), Some(Card::Number(Suit::Green, 9))])
+        .with_tableau_column(0, vec![Card::Number(Suit::Red, 5)])
+        .with_tableau_column(1, vec![Card::Number(Suit::Black, 2)])
+        .with_tableau_column(2, vec![Card::Flower]);
+    let (t0, t1, t2) = (Position::Tableau(0), Position::Tableau(1), Position::Tableau(2));
+    assert_eq!(quick_move(&pf, t0), Some(Move(1, t0, Position::FreeCell(1))));
+    assert_eq!(quick_move(&pf, t1), Some(Move(1, t1, Position::Pile(1))));
+    assert_eq!(quick_move(&pf, t2), Some(Move(1, t2, Position::Flower)));
+    // Moving from one free cell to another would be pointless
+    assert_eq!(quick_move(&pf, Position::FreeCell(0)), None);
+    assert_eq!(quick_move(&pf, Position::Tableau(3)), None);
+}
@@ -0,0 +1,165 @@
+use game_logic::*;
+use libc;
+use solver::*;
+use stats::*;
+use std::fmt;
+use std::mem;
+use std::ptr;
+use std::time::Duration;
+
+// The daily challenge: everyone playing on the same (local) date gets the same deal, and gets one
+// attempt at it.
+//
+// The deal of a date is chosen like this: the date written as the number YYYYMMDD (e.g. 20261017
+// for the 17th of October 2026) is the first candidate deal number. If the solver can't win that
+// deal within DAILY_LIMITS, 100000000 is added to the candidate (1YYYYMMDD, 2YYYYMMDD, ...) until a
+// deal that can be won is found. At most MAX_DAILY_CANDIDATES candidates are tried; if none of
+// them is won, the deal is the first candidate.
+//
+// Everyone must get the same deal for a date whichever version they run, so DAILY_LIMITS must never
+// be changed, and has no time limit as that would depend on the machine. It's separate from
+// SearchLimits::default() so that one can be retuned. Changes to the solver's search can still
+// change whether a candidate is won within the limits; test_daily_deal has the deals of a few
+// dates to catch that.
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Today, in the local time zone.
+pub fn local_date() -> Date {
+    let mut tm: libc::tm = unsafe { mem::zeroed() };
+    unsafe {
+        let now = libc::time(ptr::null_mut());
+        libc::localtime_r(&now, &mut tm);
+    }
+    Date { year: tm.tm_year + 1900, month: tm.tm_mon as u32 + 1, day: tm.tm_mday as u32 }
+}
+
+/// The first candidate deal number of @date.
+pub fn daily_seed(date: Date) -> u64 {
+    date.year as u64 * 10000 + date.month as u64 * 100 + date.day as u64
+}
+
+const CANDIDATE_STEP: u64 = 100_000_000;
+const MAX_DAILY_CANDIDATES: u64 = 10;
+// Don't change, see above.
+const DAILY_LIMITS: SearchLimits = SearchLimits { max_nodes: 200_000, max_time: None };
+
+/// The deal of the daily challenge of @date: the first candidate that the solver can win, or the
+/// first candidate if none of them can be won.
+pub fn daily_deal(date: Date) -> u64 {
+    (0..MAX_DAILY_CANDIDATES)
+        .map(|i| daily_seed(date) + i * CANDIDATE_STEP)
+        .find(|&deal| {
+            let playfield = make_shuffled_playfield_from_seed(deal);
+            matches!(find_solution(&playfield, DAILY_LIMITS).result, SolveResult::Solved(_))
+        })
+        .unwrap_or_else(|| daily_seed(date))
+}
+
+/// The earlier attempt of @player at the daily challenge with deal number @deal, if any.
+pub fn previous_attempt<'a>(records: &'a [GameRecord], deal: u64, player: &str) -> Option<&'a GameRecord> {
+    records.iter().find(|r| r.daily && r.deal == Some(deal) && r.player == player)
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct BestSolution {
+    /// Number of player moves.
+    pub moves: usize,
+    /// Whether it's known that there's no shorter solution.
+    pub optimal: bool,
+}
+
+// Finding the shortest solution can take a while; if it takes too long, a solution that isn't
+// necessarily the shortest one is settled for. The search keeps every state it has seen, so it
+// expands no more states than the default limits allow to keep the memory use down while the game
+// is being played.
+const SHORTEST_LIMITS: SearchLimits = SearchLimits { max_nodes: 200_000, max_time: Some(Duration::from_secs(60)) };
+
+fn player_moves(result: &SolveResult) -> Option<usize> {
+    match *result {
+        SolveResult::Solved(ref steps) => Some(steps.iter().filter(|s| !s.automatic).count()),
+        _ => None,
+    }
+}
+
+pub fn best_solution(deal: u64) -> Option<BestSolution> {
+    let playfield = make_shuffled_playfield_from_seed(deal);
+    if let Some(moves) = player_moves(&solve(&playfield, SHORTEST_LIMITS).result) {
+        return Some(BestSolution { moves, optimal: true });
+    }
+    player_moves(&find_solution(&playfield, SearchLimits::default()).result)
+        .map(|moves| BestSolution { moves, optimal: false })
+}
+
+/// The text shown after the daily challenge of @date has been played, comparing the player's
+/// @record with the @best solution.
+pub fn format_daily_result(date: Date, record: &GameRecord, best: Option<BestSolution>) -> String {
+    let outcome = match record.result {
+        GameResult::Won => format!("won in {} moves", record.moves),
+        GameResult::Lost => format!("lost after {} moves", record.moves),
+        GameResult::Abandoned => format!("gave up after {} moves", record.moves),
+    };
+    let mut text = format!("Daily challenge of {}: {} {}, time {}.", date, record.player, outcome,
+                           format_seconds(record.seconds));
+    match best {
+        Some(BestSolution { moves, optimal: true }) =>
+            text.push_str(&format!("\nThe shortest solution takes {} moves.", moves)),
+        Some(BestSolution { moves, optimal: false }) =>
+            text.push_str(&format!("\nThe best solution the solver found takes {} moves.", moves)),
+        None => {}
+    }
+    text
+}
+
+#[test]
+fn test_daily_seed() {
+    assert_eq!(daily_seed(Date { year: 2026, month: 10, day: 17 }), 20261017);
+    assert_eq!(daily_seed(Date { year: 2027, month: 1, day: 2 }), 20270102);
+    assert_eq!(Date { year: 2027, month: 1, day: 2 }.to_string(), "2027-01-02");
+}
+
+#[test]
+fn test_daily_deal() {
+    // These must never change, see DAILY_LIMITS
+    assert_eq!(daily_deal(Date { year: 2026, month: 10, day: 17 }), 20261017);
+    // The first candidate can't be won
+    assert_eq!(daily_deal(Date { year: 2027, month: 7, day: 18 }), 120270718);
+}
+
+#[test]
+fn test_previous_attempt() {
+    let record = GameRecord {
+        deal: Some(20261017),
+        player: "alice".to_string(),
+        result: GameResult::Lost,
+        moves: 40,
+        seconds: 75,
+        undos: 0,
+        hints_used: false,
+        finished_at: 1_700_000_000,
+        daily: true,
+    };
+    let not_daily = GameRecord { daily: false, player: "bob".to_string(), ..record.clone() };
+    let records = vec![not_daily, record.clone()];
+    assert_eq!(previous_attempt(&records, 20261017, "alice"), Some(&record));
+    assert_eq!(previous_attempt(&records, 20261017, "bob"), None);
+    assert_eq!(previous_attempt(&records, 20261018, "alice"), None);
+
+    let date = Date { year: 2026, month: 10, day: 17 };
+    assert_eq!(format_daily_result(date, &record, Some(BestSolution { moves: 28, optimal: true })),
+               "Daily challenge of 2026-10-17: alice lost after 40 moves, time 1:15.\n\
+                The shortest solution takes 28 moves.");
+}
+
+
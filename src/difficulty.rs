@@ -0,0 +1,192 @@
+use game_logic::*;
+use solver::*;
+use std::fmt;
+
+// Estimating how hard a deal is, from what it takes the solver to win it and from how the cards
+// are laid out.
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub enum Tier {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+}
+
+pub const TIERS: [Tier; 4] = [Tier::Easy, Tier::Medium, Tier::Hard, Tier::Expert];
+
+impl fmt::Display for Tier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match *self {
+            Tier::Easy => "Easy",
+            Tier::Medium => "Medium",
+            Tier::Hard => "Hard",
+            Tier::Expert => "Expert",
+        })
+    }
+}
+
+pub fn parse_tier(s: &str) -> Result<Tier, String> {
+    TIERS.iter().cloned().find(|tier| tier.to_string().eq_ignore_ascii_case(s))
+        .ok_or(format!("Unknown difficulty '{}' (expected easy, medium, hard or expert)", s))
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Difficulty {
+    /// Some(false) if the deal can't be won, None if the solver gave up.
+    pub solvable: Option<bool>,
+    /// Number of player moves in the solution found.
+    pub solution_moves: usize,
+    /// Average number of possible actions in the states the solver looked at.
+    pub branching: f64,
+    /// Number of subtrees the solver searched through without finding a win (see
+    /// SolveReport::dead_ends).
+    pub dead_ends: usize,
+    /// The fewest free cells for parking cards that the deal can be won with, as far as the solver
+    /// can tell (see find_solution_with_freecells()).
+    pub min_freecells: usize,
+    /// Number of cards on top of the 1s, in total.
+    pub buried_ones: usize,
+    /// Number of dragons lying on top of another dragon of the same suit.
+    pub stacked_dragons: usize,
+    /// 0 (trivial) to 100 (hardest).
+    pub score: u32,
+}
+
+impl Difficulty {
+    pub fn tier(&self) -> Tier {
+        match self.score {
+            0..=24 => Tier::Easy,
+            25..=49 => Tier::Medium,
+            50..=74 => Tier::Hard,
+            _ => Tier::Expert,
+        }
+    }
+}
+
+pub fn buried_ones(playfield: &Playfield) -> usize {
+    playfield.tableau().iter()
+        .flat_map(|col| col.iter().enumerate()
+            .filter(|&(_, &card)| matches!(card, Card::Number(_, 1)))
+            .map(move |(i, _)| col.len() - 1 - i))
+        .sum()
+}
+
+pub fn stacked_dragons(playfield: &Playfield) -> usize {
+    playfield.tableau().iter()
+        .map(|col| col.iter().enumerate()
+            .filter(|&(i, &card)| matches!(card, Card::Dragon(_)) && col[..i].contains(&card))
+            .count())
+        .sum()
+}
+
+// How hard the searches are allowed to try; the ones with fewer free cells are only needed
+// for telling apart the easy deals, so they get less.
+const RATING_LIMITS: SearchLimits = SearchLimits { max_nodes: 200_000, max_time: None };
+const FREECELL_LIMITS: SearchLimits = SearchLimits { max_nodes: 20_000, max_time: None };
+
+pub fn rate(playfield: &Playfield) -> Difficulty {
+    let report = find_solution(playfield, RATING_LIMITS);
+    let (solvable, solution_moves) = match report.result {
+        SolveResult::Solved(ref steps) => (Some(true), steps.iter().filter(|s| !s.automatic).count()),
+        SolveResult::Unsolvable => (Some(false), 0),
+        SolveResult::GaveUp => (None, 0),
+    };
+    let branching = report.actions_seen as f64 / report.nodes_expanded.max(1) as f64;
+    // The cells with flipped dragons can't be used any more.
+    let usable = playfield.freecells().iter().filter(|fc| !matches!(**fc, FreeCell::Flipped(_))).count();
+    let min_freecells = if solvable == Some(true) {
+        (0..usable)
+            .find(|&cells| matches!(find_solution_with_freecells(playfield, FREECELL_LIMITS, cells).result,
+                                    SolveResult::Solved(_)))
+            .unwrap_or(usable)
+    } else {
+        usable
+    };
+
+    let mut difficulty = Difficulty {
+        solvable,
+        solution_moves,
+        branching,
+        dead_ends: report.dead_ends,
+        min_freecells,
+        buried_ones: buried_ones(playfield),
+        stacked_dragons: stacked_dragons(playfield),
+        score: 0,
+    };
+    difficulty.score = score(&difficulty, report.nodes_expanded);
+    difficulty
+}
+
+// Combines the metrics into a single score. The weights are chosen so that the numbered deals
+// spread over the whole range; deals the solver can't win are the hardest there are.
+fn score(d: &Difficulty, nodes_expanded: usize) -> u32 {
+    if d.solvable != Some(true) {
+        return 100;
+    }
+    let effort = (nodes_expanded.max(1) as f64).log10();
+    let points = 1.6 * d.solution_moves as f64
+        + 10.0 * effort
+        + 10.0 * ((d.dead_ends + 1) as f64).log10()
+        + 12.0 * d.min_freecells as f64
+        + 0.8 * d.buried_ones as f64
+        + 5.0 * d.stacked_dragons as f64
+        - 3.0 * d.branching
+        - 78.0;
+    points.clamp(0.0, 99.0) as u32
+}
+
+/// Looks for a random deal of @tier that can be won, rating at most @attempts deals. Rating a deal
+/// can take seconds, so @progress is called with the number of deals rated so far before rating
+/// each one, and the search stops if it returns false.
+pub fn random_deal_of_tier(tier: Tier, attempts: usize, progress: &mut dyn FnMut(usize) -> bool) -> Option<u64> {
+    for rated in 0..attempts {
+        if !progress(rated) {
+            return None;
+        }
+        let deal = random_deal_number();
+        let difficulty = rate(&make_shuffled_playfield_from_seed(deal));
+        if difficulty.solvable == Some(true) && difficulty.tier() == tier {
+            return Some(deal);
+        }
+    }
+    None
+}
+
+#[test]
+fn test_structural_features() {
+    let pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Number(Suit::Red, 1), Card::Dragon(Suit::Red), Card::Dragon(Suit::Red)])
+        .with_tableau_column(1, vec![Card::Dragon(Suit::Green), Card::Number(Suit::Green, 1),
+                                     Card::Dragon(Suit::Green), Card::Dragon(Suit::Green)])
+        .with_tableau_column(2, vec![Card::Number(Suit::Black, 1)]);
+    assert_eq!(buried_ones(&pf), 2 + 2);
+    assert_eq!(stacked_dragons(&pf), 1 + 2);
+}
+
+#[test]
+fn test_parse_tier() {
+    assert_eq!(parse_tier("hard"), Ok(Tier::Hard));
+    assert_eq!(parse_tier("EXPERT"), Ok(Tier::Expert));
+    assert!(parse_tier("nightmare").is_err());
+}
+
+#[test]
+fn test_rate() {
+    let pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Number(Suit::Red, 2), Card::Number(Suit::Red, 1)]);
+    let difficulty = rate(&pf);
+    assert_eq!(difficulty.solvable, Some(true));
+    assert_eq!(difficulty.solution_moves, 0);
+    assert_eq!(difficulty.min_freecells, 0);
+    assert_eq!(difficulty.tier(), Tier::Easy);
+
+    let pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Dragon(Suit::Red), Card::Number(Suit::Black, 1)])
+        .with_tableau_column(1, vec![Card::Dragon(Suit::Red)]);
+    let difficulty = rate(&pf);
+    assert_eq!(difficulty.solvable, Some(false));
+    assert_eq!(difficulty.tier(), Tier::Expert);
+}
+
+
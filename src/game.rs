@@ -0,0 +1,533 @@
+// Playing a game on the terminal.
+use ansi_term::{Colour, Style};
+use command::*;
+use cursor::*;
+use game_logic::*;
+use game_state::*;
+use savefile::*;
+use solver::*;
+use stats::*;
+use render::*;
+use terminal::*;
+use std::env;
+use std::io;
+use std::io::Write;
+use std::process;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+fn card_name(card: Card) -> String {
+    match card {
+        Card::Number(suit, number) => format!("{:?} {}", suit, number),
+        Card::Dragon(suit) => format!("{:?} dragon", suit),
+        Card::Flower => "the flower".to_string(),
+    }
+}
+
+fn position_name(position: Position) -> String {
+    match position {
+        Position::FreeCell(i) => format!("free cell f{}", i + 1),
+        Position::Flower => "the flower spot".to_string(),
+        Position::Pile(i) => format!("pile p{}", i + 1),
+        Position::Tableau(i) => format!("column t{}", i + 1),
+    }
+}
+
+// A human-readable reason for why a move or a dragon flip was refused.
+fn describe_move_error(error: MoveError) -> String {
+    match error {
+        MoveError::SamePosition => "The source and the destination are the same".to_string(),
+        MoveError::SourceEmpty(position @ Position::FreeCell(_)) => format!("There is no card in {}", position_name(position)),
+        MoveError::SourceEmpty(position) => format!("{} is empty", position_name(position)),
+        MoveError::NotEnoughCards { position, available: 1 } =>
+            format!("{} holds only a single card", position_name(position)),
+        MoveError::NotEnoughCards { position, available } =>
+            format!("{} has only {} cards", position_name(position), available),
+        MoveError::NotARun { position, count } =>
+            format!("The top {} cards of {} are not a descending run of alternating suits", count, position_name(position)),
+        MoveError::CannotPickFromPile(_) => "Cards can't be taken back from a pile".to_string(),
+        MoveError::CannotPickFromFlower => "The flower can't be moved once it's placed".to_string(),
+        MoveError::DragonsFlipped(position) => format!("The dragons in {} are flipped over for good", position_name(position)),
+        MoveError::SingleCardOnly(position) => format!("Only a single card can be moved to {}", position_name(position)),
+        MoveError::FreeCellOccupied(position) => format!("{} is not free", position_name(position)),
+        MoveError::NotAFlower(_) => "Only the flower can be placed on the flower spot".to_string(),
+        MoveError::NotANumber(_) => "Only numbered cards can be placed on the piles".to_string(),
+        MoveError::WrongSuitOrder { card, top, position: Position::Tableau(_) } =>
+            format!("{} can't be placed on top of {}", card_name(card), card_name(top)),
+        MoveError::WrongSuitOrder { card, top, position } =>
+            format!("{} can't be placed on {} in {}", card_name(card), card_name(top), position_name(position)),
+        MoveError::PileNeedsOne(position) => format!("Only a 1 can start {}", position_name(position)),
+        MoveError::SuitHasPile(suit) => format!("There is a {:?} pile already", suit),
+        MoveError::SuitAlreadyFlipped(suit) => format!("The {:?} dragons are flipped already", suit),
+        MoveError::DragonsNotExposed { suit, exposed } =>
+            format!("Only {} of the four {:?} dragons are exposed", exposed, suit),
+        MoveError::NoFreeCellForDragons(suit) => format!("There is no free cell to flip the {:?} dragons into", suit),
+    }
+}
+
+fn find_flower(playfield: &Playfield) -> Option<Position> {
+    (0..playfield.tableau().len())
+        .find(|&ti| playfield.tableau()[ti].last() == Some(&Card::Flower))
+        .map(Position::Tableau)
+}
+
+fn describe_solution(playfield: &Playfield) -> String {
+    let report = solve(playfield, SearchLimits::default());
+    match report.result {
+        SolveResult::Solved(steps) => {
+            let moves: Vec<String> = steps.iter().filter(|s| !s.automatic).map(|s| format_action(s.action)).collect();
+            format!("Solvable in {} moves, {} steps with the automatic ones ({} states searched):\n{}",
+                    moves.len(), steps.len(), report.nodes_expanded, moves.join(", "))
+        }
+        SolveResult::Unsolvable =>
+            format!("This position can't be won (all {} reachable states searched)", report.states_seen),
+        SolveResult::GaveUp =>
+            format!("No solution found within {} states, giving up", report.nodes_expanded),
+    }
+}
+
+// The positions an action takes cards from and puts them to.
+pub fn action_positions(playfield: &Playfield, action: Action) -> Vec<Position> {
+    match action {
+        Action::Move(Move(_, from, to)) => vec![from, to],
+        Action::FlipDragon(suit) => {
+            let dragon = Card::Dragon(suit);
+            let freecells = (0..playfield.freecells().len())
+                .filter(|&fi| playfield.freecells()[fi] == FreeCell::InUse(dragon))
+                .map(Position::FreeCell);
+            let columns = (0..playfield.tableau().len())
+                .filter(|&ti| playfield.tableau()[ti].last() == Some(&dragon))
+                .map(Position::Tableau);
+            freecells.chain(columns).collect()
+        }
+    }
+}
+
+// Suggests the first move of some way to win from @playfield. Returns the message to show and the
+// positions to highlight.
+fn describe_hint(playfield: &Playfield) -> (String, Vec<Position>) {
+    let report = find_solution(playfield, SearchLimits::default());
+    match report.result {
+        SolveResult::Solved(steps) => match steps.iter().find(|s| !s.automatic) {
+            Some(step) => {
+                let moves_left = steps.iter().filter(|s| !s.automatic).count();
+                (format!("Hint: {} (found a way to win in {} moves from here)", format_action(step.action), moves_left),
+                 action_positions(playfield, step.action))
+            }
+            None => ("Nothing left to do, the game is already won".to_string(), vec![]),
+        },
+        SolveResult::Unsolvable =>
+            ("This position can no longer be won, try undoing some moves".to_string(), vec![]),
+        SolveResult::GaveUp =>
+            (format!("No way to win found within {} states, no hint this time", report.nodes_expanded), vec![]),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+// Reads a line typed at the bottom of the screen after @prompt. Returns None if it was cancelled
+// with Escape, or by erasing everything.
+pub fn read_command_line(keys: &mut KeyReader, prompt: &str) -> io::Result<Option<String>> {
+    print!("{}", prompt);
+    show_cursor(true);
+    let mut line = String::new();
+    let result = loop {
+        match keys.read_key(None)? {
+            Some(Key::Enter) => break Some(line),
+            Some(Key::Escape) | Some(Key::Interrupt) => break None,
+            Some(Key::Backspace) => match line.pop() {
+                Some(_) => print!("\x08 \x08"),
+                None => break None,
+            },
+            Some(Key::Char(c)) => {
+                line.push(c);
+                print!("{}", c);
+            }
+            _ => {}
+        }
+        io::stdout().flush()?;
+    };
+    show_cursor(false);
+    Ok(result)
+}
+
+pub fn game_title(state: &GameState) -> String {
+    match state.deal() {
+        Some(deal) => format!("Deal #{}", deal),
+        None => "Custom deal".to_string(),
+    }
+}
+
+const KEYS_HELP: &str = "\
+Arrows: move  Enter: pick up/place  Esc: cancel  1-3: flip dragons  u: undo  r: redo  h: hint  :: command
+?: help  q: quit  Mouse: click or drag to move cards, double-click to send a card to a pile or a free cell";
+
+// Clicks closer together than this make a double-click.
+const DOUBLE_CLICK_TIME: u64 = 400;
+
+// Handles a press or a release of the mouse button at (@x, @y). Pressing on cards picks them up,
+// pressing somewhere else or releasing there after dragging places them, as many of them as fit
+// like with the keyboard (see drop_move()). Returns the move to make, if any, or a message
+// explaining why nothing happens.
+fn handle_click(playfield: &Playfield, selection: &mut Option<(Position, usize)>,
+                last_click: &mut Option<(Position, Instant)>, pressed: bool, x: usize, y: usize)
+                -> Result<Option<Move>, String> {
+    let (position, cards) = match position_at(playfield, x, y) {
+        Some(found) => found,
+        None => {
+            if pressed {
+                *selection = None;
+            }
+            return Ok(None);
+        }
+    };
+    if !pressed {
+        return match *selection {
+            Some((from, count)) if from != position => {
+                *selection = None;
+                Ok(Some(drop_move(playfield, from, count, position)))
+            }
+            _ => Ok(None),
+        };
+    }
+
+    let double_click = match *last_click {
+        Some((clicked, time)) => clicked == position && time.elapsed() < Duration::from_millis(DOUBLE_CLICK_TIME),
+        None => false,
+    };
+    *last_click = if double_click { None } else { Some((position, Instant::now())) };
+    match *selection {
+        _ if double_click => {
+            *selection = None;
+            match quick_move(playfield, position) {
+                Some(m) => Ok(Some(m)),
+                None => Err(format!("There is nowhere to send the top card of {}", position_name(position))),
+            }
+        }
+        Some((from, count)) if from != position => {
+            *selection = None;
+            Ok(Some(drop_move(playfield, from, count, position)))
+        }
+        _ if cards > 0 && cards_to_pick_up(playfield, cards, position).is_ok() => {
+            *selection = Some((position, cards));
+            Ok(None)
+        }
+        _ => {
+            *selection = None;
+            match cards {
+                0 | 1 => Err(format!("Nothing to pick up from {}", position_name(position))),
+                _ => Err(format!("The top {} cards of {} are not a descending run of alternating suits",
+                                 cards, position_name(position))),
+            }
+        }
+    }
+}
+
+// The lines of @message, if any.
+pub fn message_lines(message: Option<&String>) -> Vec<String> {
+    message.map_or(vec![], |msg| msg.lines().map(|line| line.to_string()).collect())
+}
+
+// The status bar, the keys and @message, shown below the playfield.
+fn status_lines(state: &GameState, start_time: Instant, message: Option<&String>) -> Vec<String> {
+    let mut lines = vec![String::new(), Style::new().reverse().paint(format!(
+        " {}  Moves: {}  Time: {}  Step: {}/{} ", game_title(state), state.moves(),
+        format_duration(start_time.elapsed()), state.position(), state.history_len())).to_string()];
+    lines.extend(KEYS_HELP.lines().map(|line| Style::new().dimmed().paint(line).to_string()));
+    lines.extend(message_lines(message));
+    lines
+}
+
+fn draw(screen: &mut Screen, state: &GameState, highlights: &[Highlight], start_time: Instant,
+        message: Option<&String>) {
+    let mut lines = print_playfield(state.playfield(), highlights);
+    lines.extend(status_lines(state, start_time, message));
+    screen.draw(lines);
+}
+
+pub fn player_name() -> String {
+    sanitize_player_name(&env::var("USER").or_else(|_| env::var("USERNAME")).unwrap_or_default())
+}
+
+fn make_game_record(state: &GameState, result: GameResult, start_time: Instant, undos: usize, hints_used: bool)
+                    -> GameRecord {
+    GameRecord {
+        deal: state.deal(),
+        player: player_name(),
+        result,
+        moves: state.moves(),
+        seconds: start_time.elapsed().as_secs(),
+        undos,
+        hints_used,
+        finished_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+        daily: false,
+    }
+}
+
+// The record of a game left unfinished, or None if no moves were made in it.
+fn abandoned_record(state: &GameState, start_time: Instant, undos: usize, hints_used: bool) -> Option<GameRecord> {
+    if state.moves() == 0 {
+        return None;
+    }
+    Some(make_game_record(state, GameResult::Abandoned, start_time, undos, hints_used))
+}
+
+// Plays a single game to the end. Returns the records of the games for the statistics, in the
+// order they were played, and whether the last game ended by winning or losing it, rather than by
+// quitting. Loading another game in the middle counts as abandoning the one in progress; games
+// left without making a move aren't recorded.
+// The moves are animated if @animate is set.
+pub fn play_game(keys: &mut KeyReader, mut state: GameState, animate: bool) -> (Vec<GameRecord>, bool) {
+    let mut screen = Screen::new();
+    let mut message: Option<String> = None;
+    // Positions suggested by the last hint.
+    let mut hint: Vec<Position> = vec![];
+    let mut cursor = Position::Tableau(0);
+    // Where the picked up cards are from and how many of them there are.
+    let mut selection: Option<(Position, usize)> = None;
+    // The position of the last mouse click and when it happened, for detecting double-clicks.
+    let mut last_click: Option<(Position, Instant)> = None;
+    let mut start_time = Instant::now();
+    let mut undos = 0;
+    let mut hints_used = false;
+    let mut records = vec![];
+
+    let status = loop {
+        let mut highlights: Vec<Highlight> = hint.iter()
+            .map(|&position| Highlight { position, cards: 0, colour: Colour::Yellow })
+            .collect();
+        highlights.push(Highlight { position: cursor, cards: 0, colour: Colour::Cyan });
+        if let Some((position, cards)) = selection {
+            highlights.push(Highlight { position, cards, colour: Colour::Purple });
+        }
+        draw(&mut screen, &state, &highlights, start_time, message.as_ref());
+
+        let key = match keys.read_key(Some(Duration::from_secs(1))) {
+            Ok(Some(key)) => key,
+            // Redraw every second to keep the clock running.
+            Ok(None) => continue,
+            Err(_) => break None,
+        };
+        message = None;
+        hint.clear();
+
+        let direction = match key {
+            Key::Up => Some(Direction::Up),
+            Key::Down => Some(Direction::Down),
+            Key::Left => Some(Direction::Left),
+            Key::Right => Some(Direction::Right),
+            _ => None,
+        };
+        if let Some(direction) = direction {
+            cursor = move_cursor(cursor, direction);
+            continue;
+        }
+
+        let command = match key {
+            Key::Escape => {
+                selection = None;
+                continue;
+            }
+            Key::Enter => match selection {
+                None => {
+                    match longest_run(state.playfield(), cursor) {
+                        0 => message = Some(format!("Nothing to pick up from {}", position_name(cursor))),
+                        count => selection = Some((cursor, count)),
+                    }
+                    continue;
+                }
+                // Selecting the same position again picks up one card less, until there's none.
+                Some((from, count)) if from == cursor => {
+                    selection = if count > 1 { Some((from, count - 1)) } else { None };
+                    continue;
+                }
+                Some((from, count)) => Command::Action(Action::Move(drop_move(state.playfield(), from, count, cursor))),
+            },
+            Key::Mouse { pressed: true, x, y } if dragon_button_at(x, y).is_some() =>
+                Command::Action(Action::FlipDragon(dragon_button_at(x, y).unwrap())),
+            Key::Mouse { pressed, x, y } => {
+                if let Some((position, _)) = position_at(state.playfield(), x, y) {
+                    cursor = position;
+                }
+                match handle_click(state.playfield(), &mut selection, &mut last_click, pressed, x, y) {
+                    Ok(Some(m)) => Command::Action(Action::Move(m)),
+                    Ok(None) => continue,
+                    Err(err) => {
+                        message = Some(err);
+                        continue;
+                    }
+                }
+            }
+            // The dragon buttons from top to bottom.
+            Key::Char(c @ '1'..='3') => Command::Action(Action::FlipDragon(Suit::from_index(c as usize - '1' as usize))),
+            Key::Char('u') => Command::Undo,
+            Key::Char('r') => Command::Redo,
+            Key::Char('h') => Command::Hint,
+            Key::Char('?') => Command::Help,
+            Key::Char('q') | Key::Interrupt => Command::Quit,
+            Key::Char(':') => match read_command_line(keys, ":") {
+                Ok(Some(line)) => match parse_command(&line) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        message = Some(err);
+                        continue;
+                    }
+                },
+                Ok(None) => continue,
+                Err(_) => break None,
+            },
+            _ => continue,
+        };
+        // Whatever happens next, the picked up cards are put back.
+        selection = None;
+
+        let action = match command {
+            Command::Quit => break None,
+            Command::Help => {
+                message = Some(format!("Type ':' followed by a command to enter it.\n{}", HELP_TEXT));
+                continue;
+            }
+            Command::Solve => {
+                hints_used = true;
+                message = Some(describe_solution(state.playfield()));
+                continue;
+            }
+            Command::Hint => {
+                hints_used = true;
+                let (msg, positions) = describe_hint(state.playfield());
+                message = Some(msg);
+                hint = positions;
+                continue;
+            }
+            Command::Undo => {
+                if state.undo() {
+                    undos += 1;
+                } else {
+                    message = Some("Nothing to undo".to_string());
+                }
+                continue;
+            }
+            Command::Redo => {
+                if !state.redo() {
+                    message = Some("Nothing to redo".to_string());
+                }
+                continue;
+            }
+            Command::Goto(step) => {
+                // Going back in the history takes back moves just like undoing does.
+                if step < state.position() {
+                    undos += 1;
+                }
+                if !state.jump_to(step) {
+                    message = Some(format!("There are only {} steps in the game", state.history_len()));
+                }
+                continue;
+            }
+            Command::Save(path) => {
+                message = Some(match save_to_file(&path, &format_game(&state)) {
+                    Ok(()) => format!("Game saved to {}", path),
+                    Err(err) => err,
+                });
+                continue;
+            }
+            Command::Export(path) => {
+                message = Some(match save_to_file(&path, &format_board_file(state.playfield())) {
+                    Ok(()) => format!("Board saved to {}", path),
+                    Err(err) => err,
+                });
+                continue;
+            }
+            Command::Load(path) => {
+                match load_file(&path) {
+                    Ok(loaded) => {
+                        records.extend(abandoned_record(&state, start_time, undos, hints_used));
+                        state = loaded;
+                        start_time = Instant::now();
+                        undos = 0;
+                        hints_used = false;
+                        message = Some(format!("Loaded {}", path));
+                    }
+                    Err(err) => message = Some(err),
+                }
+                continue;
+            }
+            Command::PlaceFlower => match find_flower(state.playfield()) {
+                Some(from) => Action::Move(Move(1, from, Position::Flower)),
+                None => {
+                    message = Some("The flower is not on top of any tableau column".to_string());
+                    continue;
+                }
+            },
+            Command::Action(action) => action,
+        };
+
+        let before = state.playfield().clone();
+        let position = state.position();
+        if let Err(error) = state.apply(action) {
+            message = Some(format!("Illegal move: {}", describe_move_error(error)));
+        } else if animate {
+            animate_steps(&mut screen, &before, &state.steps()[position..], &status_lines(&state, start_time, None));
+        }
+
+        match state.playfield().status() {
+            GameStatus::InProgress => {}
+            status => break Some(status),
+        }
+    };
+    let status = match status {
+        Some(status) => status,
+        None => {
+            records.extend(abandoned_record(&state, start_time, undos, hints_used));
+            return (records, false);
+        }
+    };
+    let result = if status == GameStatus::Won { GameResult::Won } else { GameResult::Lost };
+    records.push(make_game_record(&state, result, start_time, undos, hints_used));
+
+    let title = game_title(&state);
+    let moves = state.moves();
+    let elapsed = format_duration(start_time.elapsed());
+    let text = if status == GameStatus::Won {
+        format!("{}: You won in {} moves, time {}!", title, moves, elapsed)
+    } else {
+        format!("{}: No legal moves left, game lost after {} moves, time {}.", title, moves, elapsed)
+    };
+    let mut lines = print_playfield(state.playfield(), &[]);
+    lines.push(String::new());
+    lines.push(Style::new().bold().paint(text).to_string());
+    screen.draw(lines);
+    (records, true)
+}
+
+// Shows @question below the finished game and waits for the answer.
+pub fn ask_yes_no(keys: &mut KeyReader, question: &str) -> bool {
+    print!("{} [y/n] ", question);
+    io::stdout().flush().unwrap();
+    matches!(keys.read_key(None), Ok(Some(Key::Char('y'))) | Ok(Some(Key::Char('Y'))))
+}
+
+pub fn enable_raw_terminal() -> RawTerminal {
+    match RawTerminal::enable() {
+        Ok(terminal) => terminal,
+        Err(err) => {
+            eprintln!("Can't set up the terminal: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+#[test]
+fn test_handle_click_drop() {
+    // Only Black 6 of the two selected cards fits on Green 7, whether dropped by dragging or by clicking.
+    let pf = Playfield::empty()
+        .with_tableau_column(1, vec![Card::Dragon(Suit::Red), Card::Number(Suit::Red, 7), Card::Number(Suit::Black, 6)])
+        .with_tableau_column(2, vec![Card::Number(Suit::Green, 7)]);
+    let expected = Move(1, Position::Tableau(1), Position::Tableau(2));
+    for &pressed in &[false, true] {
+        let mut selection = Some((Position::Tableau(1), 2));
+        assert_eq!(handle_click(&pf, &mut selection, &mut None, pressed, 22, 10), Ok(Some(expected)));
+        assert_eq!(selection, None);
+    }
+}
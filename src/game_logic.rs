@@ -1,6 +1,8 @@
 extern crate rand;
 
 use self::rand::Rng;
+use std::fmt;
+use std::slice;
 
 // The most basic building blocks - suits & cards:
 
@@ -12,11 +14,11 @@ pub enum Suit {
 }
 
 impl Suit {
-    pub fn to_index(&self) -> usize {
+    pub fn to_index(self) -> usize {
         match self {
-            Red => 0,
-            Green => 1,
-            Black => 2,
+            Suit::Red => 0,
+            Suit::Green => 1,
+            Suit::Black => 2,
         }
     }
 
@@ -40,11 +42,11 @@ pub enum Card {
 pub fn make_deck() -> Vec<Card> {
     let mut ret = Vec::<Card>::new();
     ret.push(Card::Flower);
-    for suit in vec![Suit::Red, Suit::Green, Suit::Black] {
+    for &suit in &[Suit::Red, Suit::Green, Suit::Black] {
         for number in 1..(9 + 1) {
             ret.push(Card::Number(suit, number));
         }
-        for i in 0..4 {
+        for _ in 0..4 {
             ret.push(Card::Dragon(suit));
         }
     }
@@ -52,59 +54,101 @@ pub fn make_deck() -> Vec<Card> {
     ret
 }
 
-pub fn make_shuffled_deck() -> Vec<Card> {
+/// Deals are numbered so they can be replayed and shared. The numbering must stay the same on
+/// every machine and in every version, so the shuffle doesn't depend on the rand crate. Instead:
+///  - a SplitMix64 generator (see next_splitmix64()) is seeded with the deal number,
+///  - the deck, in the order returned by make_deck(), is shuffled with Fisher-Yates: for i going
+///    from 39 down to 1, card i is swapped with card (next_splitmix64() % (i + 1)).
+///
+/// Don't change any of this, or all the deal numbers out there will refer to different deals.
+pub fn make_shuffled_deck_from_seed(seed: u64) -> Vec<Card> {
     let mut ret = make_deck();
-    rand::thread_rng().shuffle(ret.as_mut_slice());
+    let mut state = seed;
+    for i in (1..ret.len()).rev() {
+        let j = (next_splitmix64(&mut state) % (i as u64 + 1)) as usize;
+        ret.swap(i, j);
+    }
     ret
 }
 
+// The SplitMix64 pseudorandom number generator by Sebastiano Vigna.
+fn next_splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Picks a random deal number for a new game.
+pub fn random_deal_number() -> u64 {
+    rand::thread_rng().gen_range(1, 1_000_000_000)
+}
+
+pub fn make_shuffled_deck() -> Vec<Card> {
+    make_shuffled_deck_from_seed(random_deal_number())
+}
+
 #[test]
 fn test_make_deck() {
     assert_eq!(make_deck().len(), 40);
 }
 
+#[test]
+fn test_make_shuffled_deck_from_seed() {
+    let mut deck = make_shuffled_deck_from_seed(12345);
+    assert_eq!(deck, make_shuffled_deck_from_seed(12345));
+    assert!(deck != make_shuffled_deck_from_seed(12346));
+    // Same cards, different order
+    let mut sorted = make_deck();
+    sorted.sort_by_key(|c| format!("{:?}", c));
+    deck.sort_by_key(|c| format!("{:?}", c));
+    assert_eq!(deck, sorted);
+}
+
 // Then the playfield, where the cards are (duh!):
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum FreeCell {
     Free,
     InUse(Card),
-    // When four dragons are removed from the game and placed onto a free cell
-    Flipped,
+    /// When the four dragons of a suit are removed from the game and placed onto a free cell
+    Flipped(Suit),
 }
 
+/// The cards of a game and where they are. Outside of this crate, a playfield is read through the
+/// accessors below and changed only by the moves of the game; Playfield::new() builds one from its
+/// parts, checking that it could occur in a game.
 #[derive(Debug, PartialEq, Eq)]
 pub struct Playfield {
-    pub freecells: [FreeCell; 3],
-    // Which suits are flipped?
-    pub flipped_suits: [bool; 3],
-    // Should only be None or Some(Flower)
-    pub flower: Option<Card>,
-    // Topmost card of the pile
-    pub piles: [Option<Card>; 3],
-    // Main playfield, works as a stack (so topmost card last in the Vec)
-    pub tableau: [Vec<Card>; 8],
+    pub(crate) freecells: [FreeCell; 3],
+    /// Which suits are flipped? Kept in sync with the flipped free cells.
+    pub(crate) flipped_suits: [bool; 3],
+    /// Should only be None or Some(Flower)
+    pub(crate) flower: Option<Card>,
+    /// Topmost card of the pile
+    pub(crate) piles: [Option<Card>; 3],
+    /// Main playfield, works as a stack (so topmost card last in the Vec)
+    pub(crate) tableau: [Vec<Card>; 8],
 }
 
 impl Clone for Playfield {
     // Stupid boilerplate function
     fn clone(&self) -> Playfield {
         let mut tmp = [vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![]];
-        for i in 0..tmp.len() {
-            tmp[i] = self.tableau[i].clone();
-        }
+        tmp.clone_from_slice(&self.tableau);
         Playfield {
-            freecells: self.freecells.clone(),
-            flipped_suits: self.flipped_suits.clone(),
+            freecells: self.freecells,
+            flipped_suits: self.flipped_suits,
             flower: self.flower,
-            piles: self.piles.clone(),
+            piles: self.piles,
             tableau: tmp,
         }
     }
 }
 
 impl Playfield {
-    fn empty() -> Playfield {
+    pub fn empty() -> Playfield {
         Playfield {
             freecells: [FreeCell::Free, FreeCell::Free, FreeCell::Free],
             flipped_suits: [false, false, false],
@@ -114,7 +158,35 @@ impl Playfield {
         }
     }
 
-    fn with_freecell_cards(&self, cards: Vec<Card>, rest: FreeCell) -> Playfield {
+    pub fn freecells(&self) -> &[FreeCell; 3] {
+        &self.freecells
+    }
+
+    /// Which suits are flipped, indexed by Suit::to_index().
+    pub fn flipped_suits(&self) -> &[bool; 3] {
+        &self.flipped_suits
+    }
+
+    pub fn flower(&self) -> Option<Card> {
+        self.flower
+    }
+
+    /// The topmost card of each pile.
+    pub fn piles(&self) -> &[Option<Card>; 3] {
+        &self.piles
+    }
+
+    /// The tableau columns, each from the bottom card to the top card.
+    pub fn tableau(&self) -> &[Vec<Card>; 8] {
+        &self.tableau
+    }
+
+    // The with_ functions below are for setting up positions, e.g. in tests. Unlike new(), they
+    // don't check that the playfield they return is valid.
+
+    /// A copy of the playfield with @cards in the first free cells and the rest of them set to
+    /// @rest.
+    pub fn with_freecell_cards(&self, cards: Vec<Card>, rest: FreeCell) -> Playfield {
         let mut pf = self.clone();
         for i in 0..pf.freecells.len() {
             if i < cards.len() {
@@ -126,17 +198,42 @@ impl Playfield {
         pf
     }
 
-    fn with_tableau_column(&self, column: usize, cards: Vec<Card>) -> Playfield {
+    /// A copy of the playfield with the free cell @index set to @freecell. A flipped free cell marks
+    /// its suit as flipped too.
+    pub fn with_freecell(&self, index: usize, freecell: FreeCell) -> Playfield {
+        let mut pf = self.clone();
+        pf.freecells[index] = freecell;
+        if let FreeCell::Flipped(suit) = freecell {
+            pf.flipped_suits[suit.to_index()] = true;
+        }
+        pf
+    }
+
+    /// A copy of the playfield with @piles as the topmost cards of the piles.
+    pub fn with_piles(&self, piles: [Option<Card>; 3]) -> Playfield {
+        let mut pf = self.clone();
+        pf.piles = piles;
+        pf
+    }
+
+    /// A copy of the playfield with the tableau column @column holding @cards, bottom card first.
+    pub fn with_tableau_column(&self, column: usize, cards: Vec<Card>) -> Playfield {
         let mut pf = self.clone();
         pf.tableau[column] = cards;
         pf
     }
 }
 
-// Creates a shuffled, initial state of the game.
-// That is, all the 40 cards are evenly shuffled into the 8 tableau columns and the rest is empty.
+/// Creates a shuffled, initial state of the game.
+/// That is, all the 40 cards are evenly shuffled into the 8 tableau columns and the rest is empty.
 pub fn make_shuffled_playfield() -> Playfield {
-    let mut deck = make_shuffled_deck();
+    make_shuffled_playfield_from_seed(random_deal_number())
+}
+
+/// Creates the initial state of the game for deal number @seed, see make_shuffled_deck_from_seed().
+/// The cards are dealt row by row, left to right, so card n of the deck goes to column n % 8.
+pub fn make_shuffled_playfield_from_seed(seed: u64) -> Playfield {
+    let deck = make_shuffled_deck_from_seed(seed);
     let mut ret = Playfield::empty();
 
     for col in 0..8 {
@@ -147,30 +244,193 @@ pub fn make_shuffled_playfield() -> Playfield {
     ret
 }
 
+// Sanity checking of playfields. The with_ functions and saved boards can build a playfield that
+// could never occur in a game:
+
+/// The tallest possible tableau column: a full column of 5 cards with a 9 on top, and a run of
+/// 8 cards down to 1 placed on it.
+pub const MAX_COLUMN_HEIGHT: usize = 13;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ValidationError {
+    /// A card of the deck is nowhere on the playfield.
+    MissingCard(Card),
+    /// A card is on the playfield more than once (the extra copies are reported).
+    DuplicateCard(Card),
+    /// The pile with the given index has something else than a numbered card on it.
+    NonNumberOnPile(usize),
+    /// Two piles have cards of the same suit.
+    SameSuitPiles(Suit),
+    /// A card that should be underneath the top card of a pile is elsewhere on the playfield.
+    PileOutOfOrder(Card),
+    /// The flower spot holds something else than the flower.
+    NonFlowerOnFlowerSpot(Card),
+    /// The number of free cells flipped for the suit isn't one if the suit is in @flipped_suits and
+    /// zero otherwise.
+    FlippedSuitMismatch { suit: Suit, flipped_cells: usize },
+    /// A suit is marked as flipped, but not all four of its dragons are gone.
+    DragonOfFlippedSuit(Suit),
+    /// The tableau column with the given index is taller than MAX_COLUMN_HEIGHT.
+    ColumnTooTall(usize),
+    /// The tableau column with the given index has more cards than were dealt into it, but the
+    /// extra ones don't form a run.
+    UnreachableColumn(usize),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::MissingCard(card) => write!(f, "{:?} is missing", card),
+            ValidationError::DuplicateCard(card) => write!(f, "{:?} appears more than once", card),
+            ValidationError::NonNumberOnPile(pi) => write!(f, "Pile {} holds a card that isn't numbered", pi + 1),
+            ValidationError::SameSuitPiles(suit) => write!(f, "More than one pile of {:?}", suit),
+            ValidationError::PileOutOfOrder(card) => write!(f, "{:?} should be under the top of its pile", card),
+            ValidationError::NonFlowerOnFlowerSpot(card) => write!(f, "{:?} is on the flower spot", card),
+            ValidationError::FlippedSuitMismatch { suit, flipped_cells } =>
+                write!(f, "{} free cells are flipped for {:?} dragons", flipped_cells, suit),
+            ValidationError::DragonOfFlippedSuit(suit) => write!(f, "{:?} dragons are flipped but still in play", suit),
+            ValidationError::ColumnTooTall(ti) => write!(f, "Column {} is too tall", ti + 1),
+            ValidationError::UnreachableColumn(ti) => write!(f, "Column {} can't be reached by playing", ti + 1),
+        }
+    }
+}
+
+impl Playfield {
+    /// Builds a playfield out of its parts, checking that it is valid (see validate()).
+    pub fn new(freecells: [FreeCell; 3], flipped_suits: [bool; 3], flower: Option<Card>,
+               piles: [Option<Card>; 3], tableau: [Vec<Card>; 8]) -> Result<Playfield, Vec<ValidationError>> {
+        let pf = Playfield { freecells, flipped_suits, flower, piles, tableau };
+        pf.validate()?;
+        Ok(pf)
+    }
+
+    /// Checks that the playfield could occur in an actual game:
+    ///  - every card of make_deck() is on the playfield exactly once, counting each pile as
+    ///    holding all the cards of its suit up to its top card, and each flipped suit as holding
+    ///    its four dragons,
+    ///  - the piles hold numbered cards of different suits, with the lower cards of the suit
+    ///    nowhere else on the playfield,
+    ///  - @flipped_suits agrees with the flipped free cells,
+    ///  - the tableau columns have a shape that can be reached by playing.
+    ///
+    /// All the problems found are returned.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = vec![];
+        // All the cards on the playfield.
+        let mut found: Vec<Card> = vec![];
+
+        for fc in self.freecells.iter() {
+            if let FreeCell::InUse(card) = *fc {
+                found.push(card);
+            }
+        }
+        for col in self.tableau.iter() {
+            found.extend(col.iter().cloned());
+        }
+        if let Some(card) = self.flower {
+            if card != Card::Flower {
+                errors.push(ValidationError::NonFlowerOnFlowerSpot(card));
+            }
+            found.push(card);
+        }
+
+        let mut pile_suits = [false; 3];
+        for (pi, pile) in self.piles.iter().enumerate() {
+            match *pile {
+                Some(Card::Number(suit, number)) => {
+                    if pile_suits[suit.to_index()] {
+                        errors.push(ValidationError::SameSuitPiles(suit));
+                    }
+                    pile_suits[suit.to_index()] = true;
+                    for n in 1..number {
+                        if found.contains(&Card::Number(suit, n)) {
+                            errors.push(ValidationError::PileOutOfOrder(Card::Number(suit, n)));
+                        }
+                    }
+                    found.extend((1..number + 1).map(|n| Card::Number(suit, n)));
+                }
+                Some(card) => {
+                    errors.push(ValidationError::NonNumberOnPile(pi));
+                    found.push(card);
+                }
+                None => {}
+            }
+        }
+
+        for i in 0..self.flipped_suits.len() {
+            let suit = Suit::from_index(i);
+            let flipped_cells = self.freecells.iter().filter(|&&fc| fc == FreeCell::Flipped(suit)).count();
+            if flipped_cells != self.flipped_suits[i] as usize {
+                errors.push(ValidationError::FlippedSuitMismatch { suit, flipped_cells });
+            }
+            if !self.flipped_suits[i] {
+                continue;
+            }
+            if found.contains(&Card::Dragon(suit)) {
+                errors.push(ValidationError::DragonOfFlippedSuit(suit));
+            }
+            found.extend(vec![Card::Dragon(suit); 4]);
+        }
+
+        let deck = make_deck();
+        for (i, &card) in deck.iter().enumerate() {
+            // The dragons come in fours, check them only once.
+            if deck[..i].contains(&card) {
+                continue;
+            }
+            let expected = deck.iter().filter(|&&c| c == card).count();
+            let count = found.iter().filter(|&&c| c == card).count();
+            if count < expected {
+                errors.push(ValidationError::MissingCard(card));
+            } else if count > expected {
+                errors.push(ValidationError::DuplicateCard(card));
+            }
+        }
+
+        for (ti, col) in self.tableau.iter().enumerate() {
+            if col.len() > MAX_COLUMN_HEIGHT {
+                errors.push(ValidationError::ColumnTooTall(ti));
+            } else if col.len() > 5 && col[4..].windows(2).any(|w| !can_place_on_top(w[1], w[0])) {
+                errors.push(ValidationError::UnreachableColumn(ti));
+            }
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
+
 // And finally, rules & logic of the game:
 
-// Available positions on the playfield where cards can be played.
-// For the 'usize' indexes, only certain values are legal.
+/// Available positions on the playfield where cards can be played.
+/// For the 'usize' indexes, only certain values are legal.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum Position {
-    // Index must be 0 .. 2 inclusive.
+    /// Index must be 0 .. 2 inclusive.
     FreeCell(usize),
     Flower,
-    // Index must be 0 .. 2 inclusive.
+    /// Index must be 0 .. 2 inclusive.
     Pile(usize),
-    // Index must be 0 .. 7 inclusive.
+    /// Index must be 0 .. 7 inclusive.
     Tableau(usize),
 }
 
-// A move simply moves a number of cards from a position to another position.
+/// A move simply moves a number of cards from a position to another position: Move(count, from, to).
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
-pub struct Move(usize, Position, Position);
+pub struct Move(pub usize, pub Position, pub Position);
 
-// Returns whether @card1 can be placed on top of @card2 on the tableau.
-// That is:
-//  - both must be numeric
-//  - suits must be different
-//  - @card1's value must be one lower than @card2's
+/// Everything a player can do to the playfield: either move cards around or flip over the four
+/// exposed dragons of a suit.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Action {
+    Move(Move),
+    FlipDragon(Suit),
+}
+
+/// Returns whether @card1 can be placed on top of @card2 on the tableau.
+/// That is:
+///  - both must be numeric
+///  - suits must be different
+///  - @card1's value must be one lower than @card2's
 pub fn can_place_on_top(card1: Card, card2: Card) -> bool {
     match (card1, card2) {
         (Card::Number(suit1, number1), Card::Number(suit2, number2)) =>
@@ -179,134 +439,196 @@ pub fn can_place_on_top(card1: Card, card2: Card) -> bool {
     }
 }
 
-// Pick up @count cards from the @playfield position @from.
-// If this half-move is not permitted by the game rules, None is returned.
-// Otherwise, a pair of the following form is returned:
-//   - 1st element is the new Playfield object with the lifted card removed
-//   - 2nd element is a vector of the picked up cards
-//
-// Attempting to pick up more cards than a position contains returns None.
-pub fn pick_up_cards(playfield: Playfield, count: usize, from: Position) -> Option<(Playfield, Vec<Card>)> {
+/// Why a move or a dragon flip isn't allowed by the game rules.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum MoveError {
+    /// The cards would be put back where they were taken from.
+    SamePosition,
+    /// There is no card at the position to pick up.
+    SourceEmpty(Position),
+    /// The position has fewer cards than were to be picked up.
+    NotEnoughCards { position: Position, available: usize },
+    /// The top @count cards of the tableau column aren't a descending run of alternating suits.
+    NotARun { position: Position, count: usize },
+    /// Cards can't be taken back from the piles...
+    CannotPickFromPile(Position),
+    /// ... nor from the flower spot...
+    CannotPickFromFlower,
+    /// ... nor from a free cell holding flipped dragons.
+    DragonsFlipped(Position),
+    /// Only tableau columns accept more than one card at a time.
+    SingleCardOnly(Position),
+    FreeCellOccupied(Position),
+    /// Something else than the flower was to be placed on the flower spot.
+    NotAFlower(Card),
+    /// Something else than a numbered card was to be placed on a pile.
+    NotANumber(Card),
+    /// @card doesn't go on top of @top at the position.
+    WrongSuitOrder { card: Card, top: Card, position: Position },
+    /// Only a 1 can be placed on an empty pile.
+    PileNeedsOne(Position),
+    /// Another pile has the suit already.
+    SuitHasPile(Suit),
+    SuitAlreadyFlipped(Suit),
+    /// Only @exposed dragons of the suit are on top of a tableau column or in a free cell.
+    DragonsNotExposed { suit: Suit, exposed: usize },
+    NoFreeCellForDragons(Suit),
+}
+
+/// Returns the @count cards that would be picked up from the @playfield position @from, without
+/// modifying anything. If this half-move is not permitted by the game rules, the reason is
+/// returned instead.
+pub fn cards_to_pick_up(playfield: &Playfield, count: usize, from: Position) -> Result<&[Card], MoveError> {
     assert!(count > 0);
-    let mut pf2: Playfield = playfield;
     match from {
         // Cards can't ever be picked up from discard piles or from the flower spot.
-        Position::Flower | Position::Pile(_) => return None,
+        Position::Flower => Err(MoveError::CannotPickFromFlower),
+        Position::Pile(_) => Err(MoveError::CannotPickFromPile(from)),
         // Freecells can only have a single card each; additionally flipped-over dragons in free cells
         // can't be messed with.
-        Position::FreeCell(fi) => match (pf2.freecells[fi], count) {
-            (FreeCell::InUse(old_card), 1) => {
-                pf2.freecells[fi] = FreeCell::Free;
-                Some((pf2, vec![old_card]))
-            }
-            _ => None,
+        Position::FreeCell(fi) => match playfield.freecells[fi] {
+            FreeCell::Free => Err(MoveError::SourceEmpty(from)),
+            FreeCell::Flipped(_) => Err(MoveError::DragonsFlipped(from)),
+            FreeCell::InUse(ref old_card) if count == 1 => Ok(slice::from_ref(old_card)),
+            FreeCell::InUse(_) => Err(MoveError::NotEnoughCards { position: from, available: 1 }),
         },
         // For a tableau position, the usual can-place-on-top-of rules apply
         // (Card must be numeric, suit must be different and value strictly decreasing by one.)
         Position::Tableau(ti) => {
-            let picked_up_cards = {
-                let old_cards = &mut pf2.tableau[ti];
-                if count > old_cards.len() {
-                    return None;
-                }
-                let idx = old_cards.len() - count;
-                old_cards.split_off(idx)
-            };
-            let mut prev_card = picked_up_cards[0];
-            for i in 1..picked_up_cards.len() {
-                if can_place_on_top(picked_up_cards[i], prev_card) {
-                    prev_card = picked_up_cards[i];
-                } else {
-                    return None
-                }
+            let old_cards = &playfield.tableau[ti];
+            if old_cards.is_empty() {
+                return Err(MoveError::SourceEmpty(from));
+            }
+            if count > old_cards.len() {
+                return Err(MoveError::NotEnoughCards { position: from, available: old_cards.len() });
+            }
+            let picked_up_cards = &old_cards[old_cards.len() - count..];
+            if picked_up_cards.windows(2).all(|w| can_place_on_top(w[1], w[0])) {
+                Ok(picked_up_cards)
+            } else {
+                Err(MoveError::NotARun { position: from, count })
             }
-            Some((pf2, picked_up_cards))
         }
     }
 }
 
-// Places the cards in @new_cards onto the position @to on the @playfield.
-// If this half-move is not permitted by the game rules, None is returned.
-// Otherwise, a new Playfield object with the cards placed appropriately is returned.
-//
-// Note: This function assumes that @new_cards only comes from the return value of pick_up_cards(),
-// otherwise non-rule-conforming behaviour may occur.
-pub fn place_cards(playfield: Playfield, new_cards: Vec<Card>, to: Position) -> Option<Playfield> {
-    let mut new_pf: Playfield = playfield;
+/// Pick up @count cards from the @playfield position @from.
+/// If this half-move is not permitted by the game rules, the reason is returned.
+/// Otherwise, a pair of the following form is returned:
+///   - 1st element is the new Playfield object with the lifted card removed
+///   - 2nd element is a vector of the picked up cards
+pub fn pick_up_cards(playfield: Playfield, count: usize, from: Position) -> Result<(Playfield, Vec<Card>), MoveError> {
+    cards_to_pick_up(&playfield, count, from)?;
+    let mut pf2: Playfield = playfield;
+    let picked_up_cards = match from {
+        Position::FreeCell(fi) => {
+            let old_card = pf2.freecells[fi];
+            pf2.freecells[fi] = FreeCell::Free;
+            match old_card {
+                FreeCell::InUse(card) => vec![card],
+                _ => unreachable!(),
+            }
+        }
+        Position::Tableau(ti) => {
+            let idx = pf2.tableau[ti].len() - count;
+            pf2.tableau[ti].split_off(idx)
+        }
+        _ => unreachable!(),
+    };
+    Ok((pf2, picked_up_cards))
+}
+
+/// Checks whether the cards in @new_cards may be placed onto the position @to on the @playfield,
+/// returning the reason if they may not.
+///
+/// Note: This function assumes that @new_cards only comes from cards_to_pick_up() or
+/// pick_up_cards(), otherwise non-rule-conforming behaviour may occur.
+pub fn check_place_cards(playfield: &Playfield, new_cards: &[Card], to: Position) -> Result<(), MoveError> {
     let bottom_card = new_cards[0];
 
     // Tableau positions can accept multiple cards, so special-case that first.
     if let Position::Tableau(ti) = to {
-        let ok = {
-            let old_cards = &mut new_pf.tableau[ti];
-            let last = old_cards.last().cloned();
-            old_cards.extend(new_cards);
-            match (last, bottom_card) {
-                // Anything can be moved into empty tableau slots
-                (None, _) => true,
-                // Otherwise, we just consider if the bottom-most card of @new_cards can be placed
-                // on the top card of the tableau pile.
-                (Some(Card::Number(dst_suit, dst_number)), Card::Number(src_suit, src_number)) => {
-                    dst_number == src_number + 1 && src_suit != dst_suit
-                }
-                _ => false,
-            }
+        return match playfield.tableau[ti].last() {
+            // Anything can be moved into empty tableau slots
+            None => Ok(()),
+            // Otherwise, we just consider if the bottom-most card of @new_cards can be placed
+            // on the top card of the tableau pile.
+            Some(&top_card) if can_place_on_top(bottom_card, top_card) => Ok(()),
+            Some(&top_card) => Err(MoveError::WrongSuitOrder { card: bottom_card, top: top_card, position: to }),
         };
-        if ok {
-            return Some(new_pf)
-        } else {
-            return None
-        }
     }
 
     // All the other positions on the board can house only one card at a time.
     if new_cards.len() != 1 {
-        return None;
+        return Err(MoveError::SingleCardOnly(to));
     }
 
-    let ok = match (to, bottom_card) {
+    match (to, bottom_card) {
         // A free freecell accepts any card, other kinds of freecells don't obviously accept anything.
-        (Position::FreeCell(fi), _) => match new_pf.freecells[fi] {
-            FreeCell::Free => {
-                new_pf.freecells[fi] = FreeCell::InUse(bottom_card);
-                true
-            }
-            _ => false,
+        (Position::FreeCell(fi), _) => match playfield.freecells[fi] {
+            FreeCell::Free => Ok(()),
+            _ => Err(MoveError::FreeCellOccupied(to)),
         },
         // The flower spot only accepts a flower.
-        (Position::Flower, Card::Flower) => {
-            new_pf.flower = Some(bottom_card);
-            true
-        }
-        // A pile spot accepts a card of the same suit and a one higher value
-        (Position::Pile(pi), Card::Number(src_suit, src_number)) => {
-            let pile = &mut new_pf.piles[pi];
-            let last = *pile;
-            *pile = Some(bottom_card);
-            match last {
-                Some(Card::Number(dst_suit, dst_number)) => src_suit == dst_suit && src_number == dst_number + 1,
-                _ => true,
-            }
-        }
-        _ => false,
-    };
-    if ok { Some(new_pf) } else { None }
+        (Position::Flower, Card::Flower) => Ok(()),
+        (Position::Flower, _) => Err(MoveError::NotAFlower(bottom_card)),
+        // A pile spot accepts a card of the same suit and a one higher value. An empty one accepts
+        // only a 1, and only if no other pile has that suit already.
+        (Position::Pile(pi), Card::Number(src_suit, src_number)) => match playfield.piles[pi] {
+            Some(Card::Number(dst_suit, dst_number)) if src_suit == dst_suit && src_number == dst_number + 1 => Ok(()),
+            Some(top) => Err(MoveError::WrongSuitOrder { card: bottom_card, top, position: to }),
+            None if src_number != 1 => Err(MoveError::PileNeedsOne(to)),
+            None if playfield.piles.iter().any(|&p| matches!(p, Some(Card::Number(suit, _)) if suit == src_suit)) =>
+                Err(MoveError::SuitHasPile(src_suit)),
+            None => Ok(()),
+        },
+        (Position::Pile(_), _) => Err(MoveError::NotANumber(bottom_card)),
+        (Position::Tableau(_), _) => unreachable!(),
+    }
 }
 
-pub fn is_legal_move(playfield: &Playfield, m: Move) -> bool {
+pub fn can_place_cards(playfield: &Playfield, new_cards: &[Card], to: Position) -> bool {
+    check_place_cards(playfield, new_cards, to).is_ok()
+}
+
+/// Places the cards in @new_cards onto the position @to on the @playfield.
+/// If this half-move is not permitted by the game rules, the reason is returned.
+/// Otherwise, a new Playfield object with the cards placed appropriately is returned.
+///
+/// Note: This function assumes that @new_cards only comes from the return value of pick_up_cards(),
+/// otherwise non-rule-conforming behaviour may occur.
+pub fn place_cards(playfield: Playfield, new_cards: Vec<Card>, to: Position) -> Result<Playfield, MoveError> {
+    check_place_cards(&playfield, &new_cards, to)?;
+    let mut new_pf: Playfield = playfield;
+    match to {
+        Position::Tableau(ti) => new_pf.tableau[ti].extend(new_cards),
+        Position::FreeCell(fi) => new_pf.freecells[fi] = FreeCell::InUse(new_cards[0]),
+        Position::Flower => new_pf.flower = Some(new_cards[0]),
+        Position::Pile(pi) => new_pf.piles[pi] = Some(new_cards[0]),
+    }
+    Ok(new_pf)
+}
+
+/// Performs the full move @m (a pick up followed by a place) on the @playfield.
+/// Returns the reason if either half of the move is not permitted by the game rules, or if the
+/// cards would be put back where they were.
+pub fn apply_move(playfield: Playfield, m: Move) -> Result<Playfield, MoveError> {
     let Move(count, from, to) = m;
-    match pick_up_cards((*playfield).clone(), count, from) {
-        None => false,
-        Some((new_pf, picked_up_cards)) => {
-            match place_cards(new_pf, picked_up_cards, to) {
-                Some(_) => true,
-                None => false,
-            }
-        }
+    if from == to {
+        return Err(MoveError::SamePosition);
     }
+    let (new_pf, picked_up_cards) = pick_up_cards(playfield, count, from)?;
+    place_cards(new_pf, picked_up_cards, to)
+}
+
+pub fn is_legal_move(playfield: &Playfield, m: Move) -> bool {
+    apply_move(playfield.clone(), m).is_ok()
 }
 
-pub fn flip_dragon(playfield: Playfield, suit: Suit) -> Option<Playfield> {
+pub fn flip_dragon(playfield: Playfield, suit: Suit) -> Result<Playfield, MoveError> {
+    if playfield.flipped_suits[suit.to_index()] {
+        return Err(MoveError::SuitAlreadyFlipped(suit));
+    }
     let mut new_pf: Playfield = playfield;
 
     let mut dst = -1isize;
@@ -315,7 +637,7 @@ pub fn flip_dragon(playfield: Playfield, suit: Suit) -> Option<Playfield> {
         match new_pf.freecells[i] {
             FreeCell::InUse(Card::Dragon(s)) if s == suit => {
                 new_pf.freecells[i] = FreeCell::Free;
-                count = count + 1;
+                count += 1;
                 dst = i as isize;
             }
             FreeCell::Free => {
@@ -328,22 +650,187 @@ pub fn flip_dragon(playfield: Playfield, suit: Suit) -> Option<Playfield> {
         match new_pf.tableau[i].last() {
             Some(&Card::Dragon(s)) if s == suit => {
                 new_pf.tableau[i].pop();
-                count = count + 1;
+                count += 1;
             }
             _ => (),
         }
     }
-    if count != 4 || dst < 0 {
-        return None;
+    if count != 4 {
+        return Err(MoveError::DragonsNotExposed { suit, exposed: count });
+    }
+    if dst < 0 {
+        return Err(MoveError::NoFreeCellForDragons(suit));
     }
-    new_pf.freecells[dst as usize] = FreeCell::Flipped;
-    Some(new_pf)
+    new_pf.freecells[dst as usize] = FreeCell::Flipped(suit);
+    new_pf.flipped_suits[suit.to_index()] = true;
+    Ok(new_pf)
 }
 
+pub fn apply_action(playfield: Playfield, action: Action) -> Result<Playfield, MoveError> {
+    match action {
+        Action::Move(m) => apply_move(playfield, m),
+        Action::FlipDragon(suit) => flip_dragon(playfield, suit),
+    }
+}
+
+/// One entry in the history of a game: an action, and whether it was done by the player or
+/// automatically by the game.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Step {
+    pub action: Action,
+    pub automatic: bool,
+}
+
+// Value of the topmost card on the pile of @suit, or 0 if there's no such pile yet.
+fn pile_value(playfield: &Playfield, suit: Suit) -> usize {
+    playfield.piles.iter().filter_map(|&p| match p {
+        Some(Card::Number(s, n)) if s == suit => Some(n),
+        _ => None,
+    }).max().unwrap_or(0)
+}
+
+/// Like the original game, some cards are moved out of the way automatically:
+///  - the flower, whenever it's exposed,
+///  - a numbered card that can go on a pile, if nothing could possibly want to be placed on top of
+///    it anymore. Only cards one lower and of a different suit can go on top of it, so this is the
+///    case when those are already on the piles (1s never need such a parking place, since they can
+///    always go on a pile directly, so 2s are always safe).
+///
+/// Returns the first such move found, if any.
+pub fn auto_move(playfield: &Playfield) -> Option<Move> {
+    let freecell_cards = (0..playfield.freecells.len()).filter_map(|fi| match playfield.freecells[fi] {
+        FreeCell::InUse(card) => Some((Position::FreeCell(fi), card)),
+        _ => None,
+    });
+    let tableau_cards = (0..playfield.tableau.len())
+        .filter_map(|ti| playfield.tableau[ti].last().map(|&card| (Position::Tableau(ti), card)));
+
+    for (from, card) in freecell_cards.chain(tableau_cards) {
+        match card {
+            Card::Flower => return Some(Move(1, from, Position::Flower)),
+            Card::Number(suit, number) => {
+                let safe = number <= 2 || [Suit::Red, Suit::Green, Suit::Black].iter()
+                    .all(|&other| other == suit || pile_value(playfield, other) >= number - 1);
+                if !safe {
+                    continue;
+                }
+                let target = if number == 1 {
+                    playfield.piles.iter().position(|p| p.is_none())
+                } else {
+                    playfield.piles.iter().position(|&p| p == Some(Card::Number(suit, number - 1)))
+                };
+                if let Some(pi) = target {
+                    return Some(Move(1, from, Position::Pile(pi)));
+                }
+            }
+            Card::Dragon(_) => {}
+        }
+    }
+    None
+}
+
+/// Performs all the automatic moves (see auto_move()) until there are no more to do.
+/// Returns the resulting playfield and the moves done, in order.
+pub fn auto_play(playfield: Playfield) -> (Playfield, Vec<Move>) {
+    let mut pf = playfield;
+    let mut moves = vec![];
+    while let Some(m) = auto_move(&pf) {
+        pf = apply_move(pf, m).unwrap();
+        moves.push(m);
+    }
+    (pf, moves)
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum GameStatus {
+    Won,
+    InProgress,
+    /// The game isn't won, but there's nothing left to do either.
+    NoLegalMoves,
+}
+
+impl Playfield {
+    /// The game is won when all the numbered cards are on the piles, all the dragons are flipped and
+    /// the flower is in its place.
+    pub fn is_won(&self) -> bool {
+        self.piles.iter().all(|&p| matches!(p, Some(Card::Number(_, 9)))) &&
+            self.flipped_suits.iter().all(|&flipped| flipped) &&
+            self.flower.is_some()
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if self.is_won() {
+            GameStatus::Won
+        } else if self.legal_moves().is_empty() {
+            GameStatus::NoLegalMoves
+        } else {
+            GameStatus::InProgress
+        }
+    }
+
+    /// Lists every legal action on the playfield, leaving out the ones that are pointless:
+    ///  - moving cards from a free cell to another free cell,
+    ///  - moving a whole tableau column into an empty column,
+    ///  - all but the first of several equivalent destinations (empty free cells, empty tableau
+    ///    columns and empty piles are interchangeable).
+    ///
+    /// Moving the flower to the flower spot is an ordinary Move to Position::Flower.
+    pub fn legal_moves(&self) -> Vec<Action> {
+        let mut ret = vec![];
+
+        for &suit in &[Suit::Red, Suit::Green, Suit::Black] {
+            if flip_dragon(self.clone(), suit).is_ok() {
+                ret.push(Action::FlipDragon(suit));
+            }
+        }
+
+        let first_free_cell = self.freecells.iter().position(|&fc| fc == FreeCell::Free);
+        let first_empty_pile = self.piles.iter().position(|p| p.is_none());
+        let first_empty_column = self.tableau.iter().position(|col| col.is_empty());
+        let mut destinations = vec![Position::Flower];
+        destinations.extend((0..self.piles.len())
+            .filter(|&pi| self.piles[pi].is_some() || Some(pi) == first_empty_pile)
+            .map(Position::Pile));
+        destinations.extend(first_free_cell.map(Position::FreeCell));
+        destinations.extend((0..self.tableau.len())
+            .filter(|&ti| !self.tableau[ti].is_empty() || Some(ti) == first_empty_column)
+            .map(Position::Tableau));
+
+        let mut sources: Vec<(usize, Position)> = (0..self.freecells.len())
+            .filter(|&fi| matches!(self.freecells[fi], FreeCell::InUse(_)))
+            .map(|fi| (1, Position::FreeCell(fi)))
+            .collect();
+        for ti in 0..self.tableau.len() {
+            let mut count = 1;
+            while cards_to_pick_up(self, count, Position::Tableau(ti)).is_ok() {
+                sources.push((count, Position::Tableau(ti)));
+                count += 1;
+            }
+        }
+
+        for &(count, from) in &sources {
+            let cards = cards_to_pick_up(self, count, from).unwrap();
+            for &to in &destinations {
+                let pointless = match (from, to) {
+                    (Position::FreeCell(_), Position::FreeCell(_)) => true,
+                    (Position::Tableau(ti), Position::Tableau(di)) =>
+                        ti == di || (self.tableau[di].is_empty() && count == self.tableau[ti].len()),
+                    _ => false,
+                };
+                if !pointless && can_place_cards(self, cards, to) {
+                    ret.push(Action::Move(Move(count, from, to)));
+                }
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
 fn make_test_playfield() -> Playfield {
     Playfield {
-        freecells: [FreeCell::Free, FreeCell::Flipped, FreeCell::InUse(Card::Dragon(Suit::Black))],
-        flipped_suits: [false, false, false],
+        freecells: [FreeCell::Free, FreeCell::Flipped(Suit::Green), FreeCell::InUse(Card::Dragon(Suit::Black))],
+        flipped_suits: [false, true, false],
         flower: Some(Card::Flower),
         piles: [None, Some(Card::Number(Suit::Green, 1)), None],
         tableau: [
@@ -389,6 +876,337 @@ fn test_is_legal_move() {
     assert!(is_legal_move(&playfield, Move(2, Position::Tableau(2), Position::Tableau(6))));
 }
 
+// Every single-card move between any two positions, on a playfield with something of every kind.
+#[test]
+fn test_is_legal_move_all_positions() {
+    let playfield = Playfield {
+        freecells: [FreeCell::InUse(Card::Number(Suit::Red, 1)), FreeCell::Free, FreeCell::Flipped(Suit::Green)],
+        flipped_suits: [false, true, false],
+        flower: None,
+        piles: [Some(Card::Number(Suit::Green, 1)), Some(Card::Number(Suit::Black, 2)), None],
+        tableau: [
+            /* 0 */ vec![],
+            /* 1 */ vec![Card::Flower],
+            /* 2 */ vec![Card::Number(Suit::Green, 2)],
+            /* 3 */ vec![Card::Number(Suit::Red, 3)],
+            /* 4 */ vec![Card::Number(Suit::Black, 3)],
+            /* 5 */ vec![Card::Number(Suit::Red, 5)],
+            /* 6 */ vec![Card::Dragon(Suit::Red)],
+            /* 7 */ vec![Card::Number(Suit::Red, 2)],
+        ]
+    };
+    // Nothing can be taken from the empty free cell, the flipped one, the flower spot, the piles or
+    // the empty column. Everything else can go to the empty free cell and the empty column.
+    let legal = [
+        // Red 1 starts a pile, and goes on Green 2
+        (Position::FreeCell(0), vec![Position::Pile(2), Position::Tableau(2)]),
+        (Position::Tableau(1), vec![Position::Flower]),
+        // Green 2 goes on Green 1, Red 3 and Black 3
+        (Position::Tableau(2), vec![Position::Pile(0), Position::Tableau(3), Position::Tableau(4)]),
+        // There's no Red pile to put Red 3 on
+        (Position::Tableau(3), vec![]),
+        (Position::Tableau(4), vec![Position::Pile(1)]),
+        // Red 5 can't start a pile
+        (Position::Tableau(5), vec![]),
+        (Position::Tableau(6), vec![]),
+        (Position::Tableau(7), vec![Position::Tableau(4)]),
+    ];
+    let positions: Vec<Position> = (0..3).map(Position::FreeCell)
+        .chain(Some(Position::Flower))
+        .chain((0..3).map(Position::Pile))
+        .chain((0..8).map(Position::Tableau))
+        .collect();
+    for &from in &positions {
+        for &to in &positions {
+            let takes_anything = to == Position::FreeCell(1) || to == Position::Tableau(0);
+            let expected = legal.iter()
+                .any(|(source, destinations)| *source == from && (takes_anything || destinations.contains(&to)));
+            assert_eq!(is_legal_move(&playfield, Move(1, from, to)), expected, "{:?} -> {:?}", from, to);
+        }
+    }
+}
+
+#[test]
+fn test_pile_rules() {
+    let pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Number(Suit::Red, 1)])
+        .with_tableau_column(1, vec![Card::Number(Suit::Red, 2)])
+        .with_tableau_column(2, vec![Card::Number(Suit::Red, 3)])
+        .with_tableau_column(3, vec![Card::Number(Suit::Green, 2)])
+        .with_tableau_column(4, vec![Card::Number(Suit::Red, 9)]);
+    // Only a 1 can start a pile
+    assert!(!is_legal_move(&pf, Move(1, Position::Tableau(4), Position::Pile(0))));
+    assert!(!is_legal_move(&pf, Move(1, Position::Tableau(1), Position::Pile(0))));
+    let pf = apply_move(pf, Move(1, Position::Tableau(0), Position::Pile(1))).unwrap();
+    // Piles grow one by one, in a single suit
+    assert!(!is_legal_move(&pf, Move(1, Position::Tableau(2), Position::Pile(1))));
+    assert!(!is_legal_move(&pf, Move(1, Position::Tableau(3), Position::Pile(1))));
+    assert!(is_legal_move(&pf, Move(1, Position::Tableau(1), Position::Pile(1))));
+    // Each suit has a single pile
+    let mut pf = pf.with_tableau_column(5, vec![Card::Number(Suit::Red, 1)]);
+    assert!(!is_legal_move(&pf, Move(1, Position::Tableau(5), Position::Pile(0))));
+    assert_eq!(apply_move(pf.clone(), Move(1, Position::Tableau(5), Position::Pile(0))),
+               Err(MoveError::SuitHasPile(Suit::Red)));
+    pf.piles[1] = None;
+    assert!(is_legal_move(&pf, Move(1, Position::Tableau(5), Position::Pile(0))));
+}
+
+#[test]
+fn test_move_errors() {
+    let playfield = make_test_playfield();
+    let error = |m: Move| apply_move(playfield.clone(), m).unwrap_err();
+    let (t0, t2, t3, t4, t6) = (Position::Tableau(0), Position::Tableau(2), Position::Tableau(3),
+                                Position::Tableau(4), Position::Tableau(6));
+    let (f0, f1, f2) = (Position::FreeCell(0), Position::FreeCell(1), Position::FreeCell(2));
+    let (p0, p1) = (Position::Pile(0), Position::Pile(1));
+
+    // Picking up
+    assert_eq!(error(Move(1, t0, t0)), MoveError::SamePosition);
+    assert_eq!(error(Move(1, t0, f0)), MoveError::SourceEmpty(t0));
+    assert_eq!(error(Move(1, f0, t0)), MoveError::SourceEmpty(f0));
+    assert_eq!(error(Move(3, t2, t0)), MoveError::NotEnoughCards { position: t2, available: 2 });
+    assert_eq!(error(Move(2, f2, t0)), MoveError::NotEnoughCards { position: f2, available: 1 });
+    assert_eq!(error(Move(2, t6, t0)), MoveError::NotARun { position: t6, count: 2 });
+    assert_eq!(error(Move(1, p1, t0)), MoveError::CannotPickFromPile(p1));
+    assert_eq!(error(Move(1, Position::Flower, t0)), MoveError::CannotPickFromFlower);
+    assert_eq!(error(Move(1, f1, t0)), MoveError::DragonsFlipped(f1));
+    // Placing
+    assert_eq!(error(Move(2, t2, f0)), MoveError::SingleCardOnly(f0));
+    assert_eq!(error(Move(1, t3, f2)), MoveError::FreeCellOccupied(f2));
+    assert_eq!(error(Move(1, t3, Position::Flower)), MoveError::NotAFlower(Card::Number(Suit::Green, 2)));
+    assert_eq!(error(Move(1, Position::Tableau(1), p0)), MoveError::NotANumber(Card::Dragon(Suit::Red)));
+    assert_eq!(error(Move(1, t4, p1)), MoveError::WrongSuitOrder {
+        card: Card::Number(Suit::Black, 2), top: Card::Number(Suit::Green, 1), position: p1 });
+    assert_eq!(error(Move(1, t3, t4)), MoveError::WrongSuitOrder {
+        card: Card::Number(Suit::Green, 2), top: Card::Number(Suit::Black, 2), position: t4 });
+    assert_eq!(error(Move(1, t3, p0)), MoveError::PileNeedsOne(p0));
+    // Flipping
+    assert_eq!(flip_dragon(playfield.clone(), Suit::Red), Err(MoveError::DragonsNotExposed { suit: Suit::Red, exposed: 1 }));
+    assert_eq!(apply_action(playfield.clone(), Action::FlipDragon(Suit::Green)), Err(MoveError::SuitAlreadyFlipped(Suit::Green)));
+}
+
+#[test]
+fn test_legal_moves() {
+    let playfield = make_test_playfield();
+    let moves = playfield.legal_moves();
+    for &action in &moves {
+        if let Action::Move(m) = action {
+            assert!(is_legal_move(&playfield, m), "{:?}", m);
+        }
+    }
+
+    // Moving (Red 4, Green 3) on top of (Black 5)
+    assert!(moves.contains(&Action::Move(Move(2, Position::Tableau(2), Position::Tableau(6)))));
+    // Moving (Green 2) on top of (Green 1)
+    assert!(moves.contains(&Action::Move(Move(1, Position::Tableau(3), Position::Pile(1)))));
+    // Only the first of the empty free cells, piles and columns are offered
+    assert!(moves.contains(&Action::Move(Move(1, Position::Tableau(6), Position::Tableau(0)))));
+    assert!(!moves.contains(&Action::Move(Move(1, Position::Tableau(6), Position::Tableau(7)))));
+    let with_one = playfield.with_tableau_column(7, vec![Card::Number(Suit::Red, 1)]);
+    assert!(with_one.legal_moves().contains(&Action::Move(Move(1, Position::Tableau(7), Position::Pile(0)))));
+    assert!(!with_one.legal_moves().contains(&Action::Move(Move(1, Position::Tableau(7), Position::Pile(2)))));
+    // Piles can only be started with a 1
+    assert!(!moves.contains(&Action::Move(Move(1, Position::Tableau(5), Position::Pile(0)))));
+    // Moving a whole column into an empty one is pointless
+    assert!(!moves.contains(&Action::Move(Move(1, Position::Tableau(1), Position::Tableau(0)))));
+    assert!(!moves.contains(&Action::Move(Move(2, Position::Tableau(2), Position::Tableau(0)))));
+    // ... as is moving between free cells
+    assert!(!moves.contains(&Action::Move(Move(1, Position::FreeCell(2), Position::FreeCell(0)))));
+    assert_eq!(moves.len(), 13);
+}
+
+#[test]
+fn test_legal_moves_flip_and_flower() {
+    let pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Dragon(Suit::Black)])
+        .with_tableau_column(1, vec![Card::Dragon(Suit::Black)])
+        .with_tableau_column(2, vec![Card::Dragon(Suit::Black)])
+        .with_tableau_column(3, vec![Card::Number(Suit::Red, 5), Card::Dragon(Suit::Black)])
+        .with_tableau_column(4, vec![Card::Flower]);
+    let moves = pf.legal_moves();
+    assert!(moves.contains(&Action::FlipDragon(Suit::Black)));
+    assert!(moves.contains(&Action::Move(Move(1, Position::Tableau(4), Position::Flower))));
+}
+
+#[test]
+fn test_status() {
+    let won = Playfield {
+        freecells: [FreeCell::Flipped(Suit::Red), FreeCell::Flipped(Suit::Green), FreeCell::Flipped(Suit::Black)],
+        flipped_suits: [true, true, true],
+        flower: Some(Card::Flower),
+        piles: [Some(Card::Number(Suit::Green, 9)), Some(Card::Number(Suit::Red, 9)), Some(Card::Number(Suit::Black, 9))],
+        tableau: [vec![], vec![], vec![], vec![], vec![], vec![], vec![], vec![]],
+    };
+    assert!(won.is_won());
+    assert_eq!(won.status(), GameStatus::Won);
+
+    let mut almost = won.clone();
+    almost.piles[2] = Some(Card::Number(Suit::Black, 8));
+    almost.tableau[4] = vec![Card::Number(Suit::Black, 9)];
+    assert!(!almost.is_won());
+    assert_eq!(almost.status(), GameStatus::InProgress);
+
+    // Black 9 is buried under a dragon and there's nowhere to put it.
+    let mut stuck = almost.clone();
+    stuck.tableau[4].push(Card::Dragon(Suit::Red));
+    for col in stuck.tableau.iter_mut() {
+        if col.is_empty() {
+            col.push(Card::Dragon(Suit::Green));
+        }
+    }
+    assert_eq!(stuck.status(), GameStatus::NoLegalMoves);
+
+    assert_eq!(make_shuffled_playfield().status(), GameStatus::InProgress);
+}
+
+#[test]
+fn test_deal_numbers_are_stable() {
+    // If this fails, the deal numbering has changed and old deal numbers no longer work.
+    let pf = make_shuffled_playfield_from_seed(1);
+    assert_eq!(pf.tableau[0], vec![Card::Dragon(Suit::Red), Card::Number(Suit::Black, 3), Card::Dragon(Suit::Red),
+                                   Card::Dragon(Suit::Black), Card::Number(Suit::Red, 3)]);
+    assert_eq!(pf, make_shuffled_playfield_from_seed(1));
+    assert!(pf.tableau.iter().all(|col| col.len() == 5));
+}
+
+#[test]
+fn test_auto_move() {
+    // The flower goes to its spot, from a free cell too
+    let pf = Playfield::empty().with_tableau_column(3, vec![Card::Flower]);
+    assert_eq!(auto_move(&pf), Some(Move(1, Position::Tableau(3), Position::Flower)));
+    let pf = Playfield::empty().with_freecell_cards(vec![Card::Dragon(Suit::Red), Card::Flower], FreeCell::Free);
+    assert_eq!(auto_move(&pf), Some(Move(1, Position::FreeCell(1), Position::Flower)));
+
+    // 1s start a pile, 2s follow right away
+    let pf = Playfield::empty().with_tableau_column(5, vec![Card::Number(Suit::Black, 1)]);
+    assert_eq!(auto_move(&pf), Some(Move(1, Position::Tableau(5), Position::Pile(0))));
+    let mut pf = Playfield::empty().with_tableau_column(5, vec![Card::Number(Suit::Black, 2)]);
+    pf.piles[2] = Some(Card::Number(Suit::Black, 1));
+    assert_eq!(auto_move(&pf), Some(Move(1, Position::Tableau(5), Position::Pile(2))));
+
+    // Red 3 could still be needed for parking Green 2 ...
+    let mut pf = Playfield::empty().with_tableau_column(0, vec![Card::Number(Suit::Red, 3)]);
+    pf.piles = [Some(Card::Number(Suit::Red, 2)), Some(Card::Number(Suit::Green, 1)), Some(Card::Number(Suit::Black, 2))];
+    assert_eq!(auto_move(&pf), None);
+    // ... but not anymore once it's on the pile.
+    pf.piles[1] = Some(Card::Number(Suit::Green, 2));
+    assert_eq!(auto_move(&pf), Some(Move(1, Position::Tableau(0), Position::Pile(0))));
+
+    // Buried cards and dragons stay put
+    let pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Flower, Card::Dragon(Suit::Green)])
+        .with_tableau_column(1, vec![Card::Number(Suit::Red, 1), Card::Number(Suit::Green, 5)]);
+    assert_eq!(auto_move(&pf), None);
+}
+
+#[test]
+fn test_auto_play() {
+    let pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Number(Suit::Red, 2), Card::Flower, Card::Number(Suit::Red, 1)]);
+    let (pf, moves) = auto_play(pf);
+    assert_eq!(moves, vec![
+        Move(1, Position::Tableau(0), Position::Pile(0)),
+        Move(1, Position::Tableau(0), Position::Flower),
+        Move(1, Position::Tableau(0), Position::Pile(0)),
+    ]);
+    assert_eq!(pf.piles[0], Some(Card::Number(Suit::Red, 2)));
+    assert_eq!(pf.flower, Some(Card::Flower));
+    assert!(pf.tableau[0].is_empty());
+}
+
+#[test]
+fn test_validate() {
+    let pf = make_shuffled_playfield_from_seed(7);
+    assert_eq!(pf.validate(), Ok(()));
+    assert!(Playfield::new(pf.freecells, pf.flipped_suits, pf.flower, pf.piles, pf.tableau.clone()).is_ok());
+
+    // Flipping dragons keeps the playfield valid.
+    let mut fillers: Vec<Card> = make_deck().into_iter()
+        .filter(|&c| c != Card::Dragon(Suit::Green) && c != Card::Dragon(Suit::Red))
+        .collect();
+    let mut pf = Playfield::empty();
+    for ti in 0..8 {
+        let idx = fillers.len() - 4;
+        pf.tableau[ti] = fillers.split_off(idx);
+    }
+    for ti in 0..4 {
+        pf.tableau[ti].push(Card::Dragon(Suit::Green));
+    }
+    pf.tableau[4].push(Card::Dragon(Suit::Red));
+    pf.tableau[5].push(Card::Dragon(Suit::Red));
+    pf.freecells = [FreeCell::InUse(Card::Dragon(Suit::Red)), FreeCell::InUse(Card::Dragon(Suit::Red)), FreeCell::Free];
+    assert_eq!(pf.validate(), Ok(()));
+    let pf = flip_dragon(pf, Suit::Green).unwrap();
+    assert_eq!(pf.validate(), Ok(()));
+    let pf = flip_dragon(pf, Suit::Red).unwrap();
+    assert_eq!(pf.validate(), Ok(()));
+}
+
+// @playfield with @old replaced by @new in the tableau.
+#[cfg(test)]
+fn with_card_replaced(playfield: &Playfield, old: Card, new: Card) -> Playfield {
+    let mut pf = playfield.clone();
+    for card in pf.tableau.iter_mut().flat_map(|col| col.iter_mut()) {
+        if *card == old {
+            *card = new;
+        }
+    }
+    pf
+}
+
+#[test]
+fn test_validate_errors() {
+    let good = make_shuffled_playfield_from_seed(7);
+
+    let pf = with_card_replaced(&good, Card::Number(Suit::Green, 3), Card::Number(Suit::Red, 5));
+    assert_eq!(pf.validate(), Err(vec![ValidationError::DuplicateCard(Card::Number(Suit::Red, 5)),
+                                       ValidationError::MissingCard(Card::Number(Suit::Green, 3))]));
+    // A fifth dragon of a suit.
+    let pf = with_card_replaced(&good, Card::Number(Suit::Black, 8), Card::Dragon(Suit::Green));
+    assert_eq!(pf.validate(), Err(vec![ValidationError::DuplicateCard(Card::Dragon(Suit::Green)),
+                                       ValidationError::MissingCard(Card::Number(Suit::Black, 8))]));
+
+    let mut pf = good.clone();
+    pf.flipped_suits[1] = true;
+    let errors = pf.validate().unwrap_err();
+    assert!(errors.contains(&ValidationError::FlippedSuitMismatch { suit: Suit::Green, flipped_cells: 0 }));
+    assert!(errors.contains(&ValidationError::DragonOfFlippedSuit(Suit::Green)));
+    assert!(errors.contains(&ValidationError::DuplicateCard(Card::Dragon(Suit::Green))));
+
+    // A flipped free cell has to be of a flipped suit
+    let mut pf = Playfield::empty().with_freecell_cards(vec![], FreeCell::Flipped(Suit::Red));
+    pf.flipped_suits = [false, true, false];
+    let errors = pf.validate().unwrap_err();
+    assert!(errors.contains(&ValidationError::FlippedSuitMismatch { suit: Suit::Red, flipped_cells: 3 }));
+    assert!(errors.contains(&ValidationError::FlippedSuitMismatch { suit: Suit::Green, flipped_cells: 0 }));
+
+    let mut pf = good.clone();
+    for col in pf.tableau.iter_mut() {
+        col.retain(|&c| c != Card::Number(Suit::Red, 2));
+    }
+    pf.piles[0] = Some(Card::Number(Suit::Red, 2));
+    assert_eq!(pf.validate(), Err(vec![ValidationError::PileOutOfOrder(Card::Number(Suit::Red, 1)),
+                                       ValidationError::DuplicateCard(Card::Number(Suit::Red, 1))]));
+
+    let mut pf = Playfield::empty();
+    pf.piles = [Some(Card::Dragon(Suit::Red)), Some(Card::Number(Suit::Black, 9)), Some(Card::Number(Suit::Black, 9))];
+    pf.flower = Some(Card::Dragon(Suit::Red));
+    let errors = pf.validate().unwrap_err();
+    assert!(errors.contains(&ValidationError::NonNumberOnPile(0)));
+    assert!(errors.contains(&ValidationError::SameSuitPiles(Suit::Black)));
+    assert!(errors.contains(&ValidationError::NonFlowerOnFlowerSpot(Card::Dragon(Suit::Red))));
+    assert!(errors.contains(&ValidationError::MissingCard(Card::Flower)));
+
+    let mut pf = good.clone();
+    let extra = pf.tableau[1].split_off(0);
+    pf.tableau[0].extend(extra);
+    let errors = pf.validate().unwrap_err();
+    assert_eq!(errors, vec![ValidationError::UnreachableColumn(0)]);
+    let extra = pf.tableau[2].split_off(0);
+    pf.tableau[0].extend(extra);
+    assert_eq!(pf.validate(), Err(vec![ValidationError::ColumnTooTall(0)]));
+}
+
 #[test]
 fn test_flip_dragons_on_top_of_each_other() {
     // Can't flip since two dragons are on top of each other
@@ -396,28 +1214,40 @@ fn test_flip_dragons_on_top_of_each_other() {
         .with_tableau_column(0, vec![Card::Dragon(Suit::Black), Card::Dragon(Suit::Black)])
         .with_tableau_column(1, vec![Card::Dragon(Suit::Black)])
         .with_tableau_column(2, vec![Card::Dragon(Suit::Black)]);
-    assert!(flip_dragon(pf, Suit::Black) == None);
+    assert_eq!(flip_dragon(pf, Suit::Black), Err(MoveError::DragonsNotExposed { suit: Suit::Black, exposed: 3 }));
 }
 
 #[test]
 fn test_flip_dragons_no_space() {
     // No room in free cells, can't flip
-    let pf = Playfield::empty().with_freecell_cards(vec![], FreeCell::Flipped)
+    let pf = Playfield::empty().with_freecell_cards(vec![], FreeCell::Flipped(Suit::Red))
         .with_tableau_column(0, vec![Card::Dragon(Suit::Black)])
         .with_tableau_column(1, vec![Card::Dragon(Suit::Black)])
         .with_tableau_column(2, vec![Card::Dragon(Suit::Black)])
         .with_tableau_column(3, vec![Card::Dragon(Suit::Black)]);
-    assert!(flip_dragon(pf, Suit::Black) == None);
+    assert_eq!(flip_dragon(pf, Suit::Black), Err(MoveError::NoFreeCellForDragons(Suit::Black)));
 }
 
 #[test]
 fn test_flip_dragons_all_in_freecells() {
     let pf = Playfield::empty().with_freecell_cards(vec![], FreeCell::InUse(Card::Dragon(Suit::Red)))
         .with_tableau_column(0, vec![Card::Flower, Card::Dragon(Suit::Red)]);
-    if let Some(npf) = flip_dragon(pf, Suit::Red) {
-        assert_eq!(npf.freecells, [FreeCell::Free, FreeCell::Free, FreeCell::Flipped]);
-        assert_eq!(npf.tableau[0], vec![Card::Flower]);
-    } else {
-        assert!(false);
-    }
+    let npf = flip_dragon(pf, Suit::Red).unwrap();
+    assert_eq!(npf.freecells, [FreeCell::Free, FreeCell::Free, FreeCell::Flipped(Suit::Red)]);
+    assert_eq!(npf.flipped_suits, [true, false, false]);
+    assert_eq!(npf.tableau[0], vec![Card::Flower]);
+}
+
+#[test]
+fn test_flip_dragons_twice() {
+    // A suit that's flipped already can't be flipped again, even if its dragons were to show up
+    let mut pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Dragon(Suit::Green)])
+        .with_tableau_column(1, vec![Card::Dragon(Suit::Green)])
+        .with_tableau_column(2, vec![Card::Dragon(Suit::Green)])
+        .with_tableau_column(3, vec![Card::Dragon(Suit::Green)]);
+    pf.freecells[0] = FreeCell::Flipped(Suit::Green);
+    pf.flipped_suits[Suit::Green.to_index()] = true;
+    assert_eq!(flip_dragon(pf.clone(), Suit::Green), Err(MoveError::SuitAlreadyFlipped(Suit::Green)));
+    assert!(!pf.legal_moves().contains(&Action::FlipDragon(Suit::Green)));
 }
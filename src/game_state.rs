@@ -0,0 +1,245 @@
+use game_logic::*;
+
+/// A game in progress: the starting deal, every step taken since then and the current playfield.
+///
+/// All the playfields along the way are kept around, so undoing and redoing is just a matter of
+/// moving back and forth in the history. Steps that have been undone stay around for redoing until
+/// a new action is made.
+#[derive(Debug, Clone)]
+pub struct GameState {
+    // The deal number, if the game was started from a numbered deal.
+    deal: Option<u64>,
+    steps: Vec<Step>,
+    // playfields[i] is the playfield after the first i steps, so playfields[0] is the deal itself.
+    playfields: Vec<Playfield>,
+    // How many of the steps are currently applied.
+    position: usize,
+}
+
+impl GameState {
+    /// Starts a new game from the deal @playfield. Any cards that the game moves automatically are
+    /// moved right away.
+    pub fn new(playfield: Playfield) -> GameState {
+        let mut state = GameState { deal: None, steps: vec![], playfields: vec![playfield], position: 0 };
+        state.auto_play();
+        state
+    }
+
+    /// Starts a new game from deal number @deal, see make_shuffled_playfield_from_seed().
+    pub fn from_deal(deal: u64) -> GameState {
+        GameState { deal: Some(deal), ..GameState::new(make_shuffled_playfield_from_seed(deal)) }
+    }
+
+    pub fn deal(&self) -> Option<u64> {
+        self.deal
+    }
+
+    pub fn initial(&self) -> &Playfield {
+        &self.playfields[0]
+    }
+
+    pub fn playfield(&self) -> &Playfield {
+        &self.playfields[self.position]
+    }
+
+    /// The steps leading to the current playfield.
+    pub fn steps(&self) -> &[Step] {
+        &self.steps[..self.position]
+    }
+
+    /// Total number of steps in the history, including the ones that can be redone.
+    pub fn history_len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Number of actions made by the player to get to the current playfield.
+    pub fn moves(&self) -> usize {
+        self.steps().iter().filter(|s| !s.automatic).count()
+    }
+
+    /// Total number of actions made by the player in the history, including the ones that can be
+    /// redone.
+    pub fn history_moves(&self) -> usize {
+        self.steps.iter().filter(|s| !s.automatic).count()
+    }
+
+    fn push_step(&mut self, step: Step, new_pf: Playfield) {
+        self.steps.truncate(self.position);
+        self.playfields.truncate(self.position + 1);
+        self.steps.push(step);
+        self.playfields.push(new_pf);
+        self.position += 1;
+    }
+
+    fn auto_play(&mut self) {
+        while let Some(m) = auto_move(self.playfield()) {
+            let new_pf = apply_move(self.playfield().clone(), m).unwrap();
+            self.push_step(Step { action: Action::Move(m), automatic: true }, new_pf);
+        }
+    }
+
+    /// Makes the player's @action followed by the automatic moves, throwing away anything that could
+    /// have been redone. Returns why (and changes nothing) if the action isn't legal.
+    pub fn apply(&mut self, action: Action) -> Result<(), MoveError> {
+        let new_pf = apply_action(self.playfield().clone(), action)?;
+        self.push_step(Step { action, automatic: false }, new_pf);
+        self.auto_play();
+        Ok(())
+    }
+
+    /// Goes back to the state after the first @position steps. The steps after it are kept
+    /// for redoing. Returns false if there aren't that many steps in the history.
+    pub fn jump_to(&mut self, position: usize) -> bool {
+        if position > self.steps.len() {
+            return false;
+        }
+        self.position = position;
+        true
+    }
+
+    /// Goes to the state after the player's first @moves actions and the automatic moves they
+    /// caused. Returns false if there aren't that many actions in the history.
+    pub fn jump_to_move(&mut self, moves: usize) -> bool {
+        if moves > self.history_moves() {
+            return false;
+        }
+        // Right before the next action of the player, or at the end of the history.
+        let position = self.steps.iter().enumerate()
+            .filter(|&(_, s)| !s.automatic)
+            .map(|(i, _)| i)
+            .nth(moves)
+            .unwrap_or(self.steps.len());
+        self.jump_to(position)
+    }
+
+    /// Takes back the player's last action, along with the automatic moves it caused.
+    /// Returns false if there's nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.steps().iter().rposition(|s| !s.automatic) {
+            Some(idx) => self.jump_to(idx),
+            None => false,
+        }
+    }
+
+    /// Redoes the next undone action of the player, along with its automatic moves.
+    /// Returns false if there's nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        if self.position == self.steps.len() {
+            return false;
+        }
+        let mut position = self.position + 1;
+        while position < self.steps.len() && self.steps[position].automatic {
+            position += 1;
+        }
+        self.jump_to(position)
+    }
+}
+
+#[cfg(test)]
+fn make_test_game() -> GameState {
+    // Black 1 goes to a pile right away; moving Red 4 out of the way releases Red 1 and Red 2.
+    let pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Number(Suit::Red, 2), Card::Number(Suit::Red, 1), Card::Number(Suit::Red, 4)])
+        .with_tableau_column(1, vec![Card::Number(Suit::Green, 5), Card::Number(Suit::Black, 1)])
+        .with_tableau_column(2, vec![Card::Dragon(Suit::Green)]);
+    GameState::new(pf)
+}
+
+#[test]
+fn test_game_state_auto_moves() {
+    let mut state = make_test_game();
+    assert_eq!(state.steps().len(), 1);
+    assert!(state.steps()[0].automatic);
+    assert_eq!(state.moves(), 0);
+
+    assert!(state.apply(Action::Move(Move(1, Position::Tableau(0), Position::FreeCell(0)))).is_ok());
+    assert_eq!(state.moves(), 1);
+    assert_eq!(state.history_len(), 4);
+    assert!(state.playfield().tableau[0].is_empty());
+    assert_eq!(state.initial().tableau[0].len(), 3);
+
+    // Illegal actions change nothing
+    assert_eq!(state.apply(Action::Move(Move(1, Position::Tableau(0), Position::FreeCell(1)))),
+               Err(MoveError::SourceEmpty(Position::Tableau(0))));
+    assert_eq!(state.history_len(), 4);
+}
+
+#[test]
+fn test_game_state_undo_redo() {
+    let mut state = make_test_game();
+    let start = state.playfield().clone();
+    assert!(!state.undo());
+    assert!(!state.redo());
+
+    assert!(state.apply(Action::Move(Move(1, Position::Tableau(0), Position::FreeCell(0)))).is_ok());
+    let after_first = state.playfield().clone();
+    assert!(state.apply(Action::Move(Move(1, Position::Tableau(2), Position::Tableau(0)))).is_ok());
+    let after_second = state.playfield().clone();
+
+    assert!(state.undo());
+    assert_eq!(*state.playfield(), after_first);
+    assert!(state.undo());
+    assert_eq!(*state.playfield(), start);
+    assert_eq!(state.moves(), 0);
+    assert!(!state.undo());
+
+    assert!(state.redo());
+    assert_eq!(*state.playfield(), after_first);
+    assert!(state.redo());
+    assert_eq!(*state.playfield(), after_second);
+    assert!(!state.redo());
+
+    // A new action after undoing forgets the undone ones
+    assert!(state.undo());
+    assert!(state.apply(Action::Move(Move(1, Position::Tableau(2), Position::FreeCell(1)))).is_ok());
+    assert!(!state.redo());
+    assert_eq!(state.moves(), 2);
+}
+
+#[test]
+fn test_game_state_from_deal() {
+    let state = GameState::from_deal(42);
+    assert_eq!(state.deal(), Some(42));
+    assert_eq!(*state.initial(), make_shuffled_playfield_from_seed(42));
+    assert_eq!(make_test_game().deal(), None);
+}
+
+#[test]
+fn test_game_state_jump_to() {
+    let mut state = make_test_game();
+    assert!(state.apply(Action::Move(Move(1, Position::Tableau(0), Position::FreeCell(0)))).is_ok());
+    let end = state.playfield().clone();
+
+    // Individual automatic steps can be jumped to as well
+    assert!(state.jump_to(2));
+    assert_eq!(state.playfield().tableau[0], vec![Card::Number(Suit::Red, 2), Card::Number(Suit::Red, 1)]);
+    assert!(state.jump_to(0));
+    assert_eq!(state.playfield(), state.initial());
+    assert!(!state.jump_to(5));
+    assert!(state.jump_to(4));
+    assert_eq!(*state.playfield(), end);
+}
+
+#[test]
+fn test_game_state_jump_to_move() {
+    let mut state = make_test_game();
+    assert!(state.apply(Action::Move(Move(1, Position::Tableau(0), Position::FreeCell(0)))).is_ok());
+    assert!(state.apply(Action::Move(Move(1, Position::Tableau(2), Position::Tableau(0)))).is_ok());
+    let end = state.playfield().clone();
+    assert_eq!(state.history_moves(), 2);
+
+    assert!(state.jump_to_move(0));
+    assert_eq!(state.position(), 1);
+    assert!(state.jump_to_move(1));
+    assert_eq!(state.moves(), 1);
+    // The automatic moves after the action are included
+    assert_eq!(state.position(), 4);
+    assert_eq!(state.history_moves(), 2);
+    assert!(state.jump_to_move(2));
+    assert_eq!(*state.playfield(), end);
+    assert!(!state.jump_to_move(3));
+}
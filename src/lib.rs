@@ -0,0 +1,31 @@
+//! The rules of the solitaire from Shenzhen I/O, with a solver and a text format for saving games.
+//! The terminal frontend, with the statistics, the daily challenge and the rest, is the binary.
+//!
+//! The game itself is in `game_logic`: the cards, the playfield, the positions cards are moved
+//! between, the actions a player can make and the rules for those. `game_state` keeps the history
+//! of a game for undoing and redoing. `solver` searches for ways to win, and `compact` has a board
+//! that searches can make and undo actions on without allocating. `savefile` reads and writes
+//! boards, games and moves as text. The commonly needed items are also available from the root
+//! of the crate:
+//!
+//! ```
+//! use shenzen_solitaire::*;
+//!
+//! let playfield = make_shuffled_playfield_from_seed(1);
+//! let m = Move(1, Position::Tableau(0), Position::FreeCell(0));
+//! let playfield = apply_action(playfield, Action::Move(m)).unwrap();
+//! assert!(playfield.legal_moves().len() > 0);
+//! ```
+
+pub mod compact;
+pub mod game_logic;
+pub mod game_state;
+pub mod savefile;
+pub mod solver;
+
+pub use game_logic::{Action, Card, FreeCell, GameStatus, Move, MoveError, Playfield, Position, Step, Suit,
+                     ValidationError};
+pub use game_logic::{apply_action, apply_move, auto_move, auto_play, can_place_cards, can_place_on_top,
+                     cards_to_pick_up, check_place_cards, flip_dragon, is_legal_move, make_deck,
+                     make_shuffled_playfield, make_shuffled_playfield_from_seed, pick_up_cards, place_cards};
+pub use game_state::GameState;
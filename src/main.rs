@@ -1,251 +1,386 @@
-mod game_logic;
+// The terminal frontend. The rules of the game, the solver and saved games are in the library part
+// of the crate (see lib.rs).
+mod batch;
+mod command;
+mod cursor;
+mod daily;
+mod difficulty;
+mod game;
+mod render;
+mod replay;
+mod stats;
+mod terminal;
 
 extern crate ansi_term;
+extern crate libc;
+extern crate shenzen_solitaire;
 
-use ansi_term::{ANSIString, Colour, Style};
+use batch::*;
+use daily::*;
+use difficulty::*;
+use game::*;
+use replay::*;
+use shenzen_solitaire::{game_logic, game_state, savefile, solver};
 use game_logic::*;
+use game_state::*;
+use savefile::*;
+use solver::*;
+use stats::*;
+use terminal::*;
+use std::env;
 use std::io;
 use std::io::Write;
+use std::process;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
 
-fn style_of_suit(suit: Suit) -> Style {
-    match suit {
-        Suit::Red => Style::new().fg(Colour::Red),
-        Suit::Green => Style::new().fg(Colour::Green),
-        Suit::Black => Style::new().fg(Colour::Blue),
+// How many deals are rated at most when looking for one of a given difficulty, and for how long.
+const TIER_ATTEMPTS: usize = 200;
+const TIER_SEARCH_TIME: Duration = Duration::from_secs(20);
+
+// A new random deal, of difficulty @tier if one is given. The search for a deal of the difficulty
+// is shown on the current line of the terminal.
+fn new_deal(tier: Option<Tier>) -> Result<u64, String> {
+    let tier = match tier {
+        Some(tier) => tier,
+        None => return Ok(random_deal_number()),
+    };
+    let start = Instant::now();
+    let deal = random_deal_of_tier(tier, TIER_ATTEMPTS, &mut |rated| {
+        print!("\r\x1b[KLooking for a deal of difficulty {}... {} deals rated", tier, rated);
+        io::stdout().flush().unwrap();
+        start.elapsed() < TIER_SEARCH_TIME
+    });
+    print!("\r\x1b[K");
+    deal.ok_or(format!("Couldn't find a deal of difficulty {}", tier))
+}
+
+// Plays games until the player quits, recording them into the statistics file at @stats_file.
+// The new games are of difficulty @tier, if given.
+fn interactive(first_game: GameState, stats_file: &str, tier: Option<Tier>, animate: bool) {
+    let mut errors = vec![];
+    {
+        let _terminal = enable_raw_terminal();
+        let mut keys = KeyReader::new();
+        let mut state = first_game;
+        loop {
+            let (records, finished) = play_game(&mut keys, state, animate);
+            for record in records {
+                if let Err(err) = append_record(stats_file, &record) {
+                    errors.push(format!("Couldn't save the statistics: {}", err));
+                }
+            }
+            if !finished || !ask_yes_no(&mut keys, "Deal a new game?") {
+                break;
+            }
+            match new_deal(tier) {
+                Ok(deal) => state = GameState::from_deal(deal),
+                Err(err) => {
+                    errors.push(err);
+                    break;
+                }
+            }
+        }
+    }
+    // Now that the terminal is back to normal.
+    for err in errors {
+        eprintln!("{}", err);
     }
 }
 
-fn ansi_of_dragon(suit: Suit) -> String {
-    let c = match suit {
-        Suit::Red => "%",
-        Suit::Green => "&",
-        Suit::Black => "=",
+// Plays today's daily challenge, unless the player has already had their go at it.
+fn play_daily(stats_file: &str, animate: bool) {
+    let records = match load_records(stats_file) {
+        Ok(records) => records,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
     };
-    style_of_suit(suit).paint(c).to_string()
-}
+    let date = local_date();
+    println!("Preparing the daily challenge of {}...", date);
+    let deal = daily_deal(date);
+    // The shortest solution is searched for while the game is being played.
+    let best = thread::spawn(move || best_solution(deal));
 
-fn print_card_main_part(card: &Card, is_head: bool) -> Vec<String> {
-    let mut ret = vec![];
-    if is_head {
-        ret.push("╭────────╮ ".to_string());
+    if let Some(record) = previous_attempt(&records, deal, &player_name()) {
+        println!("You have already played today's challenge. Finding the shortest solution...");
+        println!("{}", format_daily_result(date, record, best.join().unwrap_or(None)));
+        return;
     }
-    ret.push(if is_head {
-        match *card {
-            Card::Dragon(s) => format!("│ {}      │ ", ansi_of_dragon(s)),
-            Card::Flower => format!("│  ~~~~  │ "),
-            Card::Number(s, n) => format!("│ {}      │ ", style_of_suit(s).paint(n.to_string()), ),
+    restore_on_panic();
+    let mut records = {
+        let _terminal = enable_raw_terminal();
+        let mut keys = KeyReader::new();
+        let (records, finished) = play_game(&mut keys, GameState::from_deal(deal), animate);
+        if finished {
+            print!("Press any key to continue.");
+            io::stdout().flush().unwrap();
+            let _ = keys.read_key(None);
         }
-    } else {
-        match *card {
-            Card::Dragon(s) => format!("│      {} │ ", ansi_of_dragon(s)),
-            Card::Flower => format!("│  ~~~~  │ "),
-            Card::Number(s, n) => format!("│      {} │ ", style_of_suit(s).paint(n.to_string()), ),
+        records
+    };
+    if records.is_empty() {
+        return println!("The daily challenge wasn't started; you can still play it today.");
+    }
+    // The first game is the challenge, even if another game was loaded in the middle of it, so
+    // that it can't be played again by abandoning it that way.
+    records[0].daily = records[0].deal == Some(deal);
+    for record in &records {
+        if let Err(err) = append_record(stats_file, record) {
+            eprintln!("Couldn't save the statistics: {}", err);
         }
-    });
-    if !is_head {
-        ret.push("╰────────╯ ".to_string());
     }
-    ret
+    let record = &records[0];
+    if record.daily {
+        println!("Finding the shortest solution...");
+        println!("{}", format_daily_result(date, record, best.join().unwrap_or(None)));
+    }
 }
 
-fn empty_column() -> Vec<String> {
-    vec![
-        "           ".to_string(),
-        "           ".to_string(),
-    ]
-}
+const USAGE: &str = "\
+Usage: shenzen-solitaire [--deal N | --load FILE | --tier TIER] [--stats-file FILE] [--animate]
+       shenzen-solitaire daily [--stats-file FILE] [--animate]
+       shenzen-solitaire stats [--player NAME] [--deal N] [--stats-file FILE]
+       shenzen-solitaire solve --from N --to N [--jobs N] [--max-nodes N] [--time-limit SECS]
+                               [--shortest] [--json]
+       shenzen-solitaire replay (--load FILE | --deal N) [--solution] [--delay SECS] [--animate]
+The daily challenge is the same deal for everyone on the same date, and can be played once a day.
 
-fn filler_column() -> Vec<String> {
-    vec![
-        "│        │ ".to_string(),
-        "│        │ ".to_string(),
-    ]
-}
+  --deal N           play deal number N instead of a random one; with stats, compare the
+                     games played on it
+  --load FILE        continue a saved game, or start from a saved board
+  --tier TIER        play random deals of the given difficulty: easy, medium, hard or expert
+  --player NAME      show the statistics of a single player only
+  --stats-file FILE  where the statistics are kept (default ~/.shenzen-solitaire-stats)
+  --animate          show the cards moving
+  --from N, --to N   the range of deals to solve, inclusive
+  --jobs N           number of deals to solve in parallel (default: number of CPUs)
+  --max-nodes N      give up on a deal after searching this many states (default 200000)
+  --time-limit SECS  give up on a deal after this many seconds
+  --shortest         find the shortest solutions, which is much slower
+  --json             write JSON lines instead of CSV
+  --solution         replay the solver's solution instead of the saved moves
+  --delay SECS       time between the moves when playing a replay (default 1)";
 
-fn print_card(card: &Card) -> Vec<String> {
-    let mut ret = vec![];
-    ret.extend(print_card_main_part(card, true));
-    ret.extend(filler_column());
-    ret.extend(filler_column());
-    ret.extend(print_card_main_part(card, false));
-    ret
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Mode {
+    Play,
+    Daily,
+    Stats,
+    Solve,
+    Replay,
 }
 
-fn print_flipped_card() -> Vec<String> {
-    let mut ret = vec![];
-    ret.push("╭────────╮ ".to_string());
-    for i in 0..6 {
-        ret.push("│▒▒▒▒▒▒▒▒│ ".to_string());
-    }
-    ret.push("╰────────╯ ".to_string());
-    ret
+struct Options {
+    mode: Mode,
+    deal: Option<u64>,
+    load: Option<String>,
+    player: Option<String>,
+    stats_file: Option<String>,
+    tier: Option<Tier>,
+    batch: BatchOptions,
+    solution: bool,
+    delay: Duration,
+    animate: bool,
 }
 
-fn print_free_card() -> Vec<String> {
-    let mut ret = vec![];
-    ret.push("╭────────╮ ".to_string());
-    for i in 0..6 {
-        ret.push("│        │ ".to_string());
-    }
-    ret.push("╰────────╯ ".to_string());
-    ret
+// Parses the value following the option @flag.
+fn parse_value<T: FromStr>(flag: &str, value: Option<&String>) -> Result<T, String> {
+    let value = value.ok_or(format!("{} needs a value", flag))?;
+    value.parse().map_err(|_| format!("Invalid value '{}' for {}", value, flag))
 }
 
-// Card drawing: each non-topmost card consists of 1 'head' piece (where 1 piece == 2 lines)
-// and the topmost card consists of 4 pieces (head, 2 filler, tail)
-//╭────────╮\ head
-//│ 2      │/
-//╭────────╮\ head
-//│ 1      │/
-//│        |\ filler 1
-//│        │/
-//│        │\ filler 2
-//│        │/
-//│      1 │\ tail
-//╰────────╯/
-// So for a stack of n cards, we always draw n + 3 pieces.
-// Exception: empty stacks are not drawn.
-
-fn print_tableau(playfield: &Playfield) {
-    let max_col_height = playfield.tableau.iter().map(|cs| cs.len()).max().unwrap();
-    let mut prints: Vec<Vec<String>> = vec![];
-    for col in 0..8 {
-        let cards_in_column = &playfield.tableau[col];
-        let column_height = cards_in_column.len();
-
-        let mut column_lines: Vec<String> = vec![];
-
-        for piece_index in 0..(max_col_height + 3) {
-            let is_head = piece_index < column_height;
-            let is_filler = !is_head && (piece_index < column_height + 2 && !cards_in_column.is_empty());
-            let is_tail = piece_index == column_height + 2 && !cards_in_column.is_empty();
-
-            if is_head {
-                column_lines.extend(print_card_main_part(cards_in_column.get(piece_index).unwrap(), true));
-            } else if is_filler {
-                column_lines.extend(filler_column())
-            } else if is_tail {
-                column_lines.extend(print_card_main_part(cards_in_column.get(piece_index - 3).unwrap(), false));
-            } else {
-                column_lines.extend(empty_column());
-            }
-        }
-        prints.push(column_lines);
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let batch = BatchOptions {
+        from: 1,
+        to: 0,
+        jobs: thread::available_parallelism().map_or(1, |n| n.get()),
+        limits: SearchLimits::default(),
+        shortest: false,
+        format: OutputFormat::Csv,
+    };
+    let mut options = Options {
+        mode: Mode::Play,
+        deal: None,
+        load: None,
+        player: None,
+        stats_file: None,
+        tier: None,
+        batch,
+        solution: false,
+        delay: Duration::from_secs(1),
+        animate: false,
+    };
+    let mut iter = args.iter().peekable();
+    match iter.peek().map(|arg| arg.as_str()) {
+        Some("daily") => options.mode = Mode::Daily,
+        Some("stats") => options.mode = Mode::Stats,
+        Some("solve") => options.mode = Mode::Solve,
+        Some("replay") => options.mode = Mode::Replay,
+        _ => {}
     }
-    for i in 0..prints[0].len() {
-        for j in 0..playfield.tableau.len() {
-            print!("{}", prints[j][i]);
-        }
-        println!();
+    if options.mode != Mode::Play {
+        iter.next();
     }
-}
-
-fn print_top(playfield: &Playfield) {
-    let mut prints: Vec<Vec<String>> = vec![];
-    for fc in playfield.freecells.iter() {
-        match fc {
-            &FreeCell::InUse(c) => {
-                prints.push(print_card(&c));
+    let mut range = (None, None);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--deal" if options.mode != Mode::Daily && options.mode != Mode::Solve =>
+                options.deal = Some(parse_value(arg, iter.next())?),
+            "--load" if options.mode == Mode::Play || options.mode == Mode::Replay => {
+                options.load = Some(iter.next().ok_or("--load needs a file name")?.clone());
+            }
+            "--tier" if options.mode == Mode::Play => {
+                options.tier = Some(parse_tier(iter.next().ok_or("--tier needs a difficulty")?)?);
             }
-            &FreeCell::Flipped => {
-                prints.push(print_flipped_card());
+            "--player" if options.mode == Mode::Stats => {
+                options.player = Some(iter.next().ok_or("--player needs a name")?.clone());
             }
-            &FreeCell::Free => {
-                prints.push(print_free_card());
+            "--stats-file" if options.mode != Mode::Solve && options.mode != Mode::Replay => {
+                options.stats_file = Some(iter.next().ok_or("--stats-file needs a file name")?.clone());
             }
+            "--from" if options.mode == Mode::Solve => range.0 = Some(parse_value(arg, iter.next())?),
+            "--to" if options.mode == Mode::Solve => range.1 = Some(parse_value(arg, iter.next())?),
+            "--jobs" if options.mode == Mode::Solve => options.batch.jobs = parse_value(arg, iter.next())?,
+            "--max-nodes" if options.mode == Mode::Solve =>
+                options.batch.limits.max_nodes = parse_value(arg, iter.next())?,
+            "--time-limit" if options.mode == Mode::Solve => {
+                let secs: f64 = parse_value(arg, iter.next())?;
+                options.batch.limits.max_time = Some(Duration::try_from_secs_f64(secs)
+                    .map_err(|_| format!("Invalid value '{}' for {}", secs, arg))?);
+            }
+            "--shortest" if options.mode == Mode::Solve => options.batch.shortest = true,
+            "--json" if options.mode == Mode::Solve => options.batch.format = OutputFormat::JsonLines,
+            "--solution" if options.mode == Mode::Replay => options.solution = true,
+            "--animate" if options.mode != Mode::Stats && options.mode != Mode::Solve => options.animate = true,
+            "--delay" if options.mode == Mode::Replay => {
+                let secs: f64 = parse_value(arg, iter.next())?;
+                options.delay = Duration::try_from_secs_f64(secs)
+                    .map_err(|_| format!("Invalid value '{}' for {}", secs, arg))?
+                    .clamp(MIN_REPLAY_DELAY, MAX_REPLAY_DELAY);
+            }
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ => return Err(format!("Unknown argument '{}'\n{}", arg, USAGE)),
         }
     }
-
-    let mut tmp = vec![];
-    // Draw flower & dragon symbols here
-    for (i, _) in playfield.flipped_suits.iter().enumerate() {
-        let style = style_of_suit(Suit::from_index(i)).bold();
-        tmp.extend(vec![
-            "  ╭─╮   ",
-            "  ╰─╯   ",
-            "        ",
-        ].iter().map(|x| style.paint(x.to_string()).to_string()));
-    }
-    tmp.pop();
-    let tmp2 = vec![
-        "              ".to_string(),
-        "|        |    ".to_string(),
-        "|        |    ".to_string(),
-        "|        |    ".to_string(),
-        "|        |    ".to_string(),
-        "|        |    ".to_string(),
-        "|        |    ".to_string(),
-        "              ".to_string(),
-    ];
-    prints.push(tmp);
-    prints.push(tmp2);
-
-    for p in playfield.piles.iter() {
-        match p {
-            &Some(c) => {
-                prints.push(print_card(&c));
+    if options.mode == Mode::Solve {
+        match range {
+            (Some(from), Some(to)) if from <= to => {
+                options.batch.from = from;
+                options.batch.to = to;
             }
-            _ => prints.push(print_free_card()),
+            (Some(_), Some(_)) => return Err("--from must not be after --to".to_string()),
+            _ => return Err(format!("solve needs --from and --to\n{}", USAGE)),
+        }
+        if options.batch.jobs == 0 {
+            return Err("--jobs must be at least 1".to_string());
         }
     }
-
-    for i in 0..prints[0].len() {
-        for j in 0..prints.len() {
-            print!("{}", prints[j][i]);
+    if options.mode == Mode::Replay {
+        match (options.deal, &options.load) {
+            (None, None) => return Err(format!("replay needs --load or --deal\n{}", USAGE)),
+            (Some(_), Some(_)) => return Err("--deal and --load can't be used together".to_string()),
+            (Some(_), None) if !options.solution => {
+                return Err("Only the solution of a deal can be replayed, use --solution".to_string())
+            }
+            _ => {}
         }
-        println!();
     }
+    Ok(options)
 }
 
-fn print_playfield(playfield: &Playfield) {
-    print_top(playfield);
-    println!();
-    print_tableau(playfield);
-}
-
-fn test_render() {
-    let render_test = Playfield {
-        //freecells: [FreeCell::Free, FreeCell::Flipped, FreeCell::InUse(Card::Dragon(Suit::Black))],
-        freecells: [FreeCell::InUse(Card::Dragon(Suit::Black)), FreeCell::Flipped, FreeCell::Free],
-        flipped_suits: [false, false, false],
-        flower: Some(Card::Flower),
-        piles: [Some(Card::Number(Suit::Red, 4)), Some(Card::Number(Suit::Green, 1)), Some(Card::Number(Suit::Black, 9))],
-        tableau: [
-            /* 0 */ vec![],
-            /* 1 */ vec![Card::Number(Suit::Red, 1)],
-            /* 2 */ vec![Card::Number(Suit::Red, 1), Card::Number(Suit::Black, 2)],
-            /* 3 */ vec![Card::Number(Suit::Red, 1), Card::Number(Suit::Black, 2), Card::Number(Suit::Green, 3)],
-            /* 4 */ vec![Card::Number(Suit::Red, 1), Card::Number(Suit::Black, 2), Card::Number(Suit::Green, 3), Card::Number(Suit::Red, 4)],
-            /* 5 */ vec![Card::Number(Suit::Red, 5), Card::Number(Suit::Black, 4)],
-            /* 6 */ vec![Card::Number(Suit::Red, 6), Card::Number(Suit::Black, 5)],
-            /* 7 */ vec![Card::Number(Suit::Red, 1), Card::Number(Suit::Black, 2), Card::Number(Suit::Green, 3), Card::Number(Suit::Red, 4), Card::Number(Suit::Black, 9), Card::Number(Suit::Black, 8), Card::Number(Suit::Black, 7), Card::Number(Suit::Black, 6), Card::Number(Suit::Black, 5), Card::Number(Suit::Black, 4), Card::Number(Suit::Black, 3), Card::Number(Suit::Black, 2), Card::Number(Suit::Black, 1), ],
-        ]
+// Replays the saved game at @load, or the solution of it or of deal number @deal if @solution is set.
+fn start_replay(load: Option<String>, deal: Option<u64>, solution: bool, delay: Duration, animate: bool) {
+    let game = match (load, deal) {
+        (Some(path), _) => load_file(&path),
+        (None, deal) => Ok(GameState::from_deal(deal.unwrap())),
     };
-    print_playfield(&render_test);
+    let game = if solution { game.and_then(|game| solution_game(&game)) } else { game };
+    match game {
+        Ok(game) => {
+            restore_on_panic();
+            replay::replay(game, delay, animate);
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
 }
 
-fn clear() {
-    print!("\x1b[H\x1b[2J");
-    io::stdout().flush();
+fn default_stats_file() -> String {
+    match env::var("HOME") {
+        Ok(home) => format!("{}/.shenzen-solitaire-stats", home),
+        Err(_) => ".shenzen-solitaire-stats".to_string(),
+    }
 }
 
-fn interactive() {
-    let playfield = make_shuffled_playfield();
-
-    loop {
-        clear();
-        print_playfield(&playfield);
-        println!();
-
-        print!("Enter move: ");
-        io::stdout().flush();
-
-        let mut buf = String::new();
-        io::stdin().read_line(&mut buf);
-        println!();
+fn show_stats(stats_file: &str, player: Option<String>, deal: Option<u64>) {
+    let mut records = match load_records(stats_file) {
+        Ok(records) => records,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    };
+    if let Some(player) = player {
+        let player = sanitize_player_name(&player);
+        records.retain(|r| r.player == player);
     }
+    println!("{}", format_report(&records, deal));
 }
 
 fn main() {
-    interactive();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(2);
+        }
+    };
+    let stats_file = options.stats_file.unwrap_or_else(default_stats_file);
+    match options.mode {
+        Mode::Daily => return play_daily(&stats_file, options.animate),
+        Mode::Stats => return show_stats(&stats_file, options.player, options.deal),
+        Mode::Solve => {
+            let stdout = io::stdout();
+            if let Err(err) = run_batch(options.batch, &mut stdout.lock()) {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+            return;
+        }
+        Mode::Replay =>
+            return start_replay(options.load, options.deal, options.solution, options.delay, options.animate),
+        Mode::Play => {}
+    }
+    let chosen = [options.deal.is_some(), options.load.is_some(), options.tier.is_some()];
+    if chosen.iter().filter(|&&chosen| chosen).count() > 1 {
+        eprintln!("Only one of --deal, --load and --tier can be used");
+        process::exit(2);
+    }
+    let first_game = match (options.deal, options.load) {
+        (_, Some(path)) => match load_file(&path) {
+            Ok(state) => state,
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        },
+        (Some(deal), None) => GameState::from_deal(deal),
+        (None, None) => {
+            match new_deal(options.tier) {
+                Ok(deal) => GameState::from_deal(deal),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    process::exit(1);
+                }
+            }
+        }
+    };
+    restore_on_panic();
+    interactive(first_game, &stats_file, options.tier, options.animate);
 }
@@ -0,0 +1,609 @@
+// Drawing the playfield on the terminal, and animating the moves on it.
+use ansi_term::{Colour, Style};
+use game_logic::*;
+use terminal::*;
+use std::thread;
+use std::time::Duration;
+
+fn style_of_suit(suit: Suit) -> Style {
+    match suit {
+        Suit::Red => Style::new().fg(Colour::Red),
+        Suit::Green => Style::new().fg(Colour::Green),
+        Suit::Black => Style::new().fg(Colour::Blue),
+    }
+}
+
+fn ansi_of_dragon(suit: Suit) -> String {
+    let c = match suit {
+        Suit::Red => "%",
+        Suit::Green => "&",
+        Suit::Black => "=",
+    };
+    style_of_suit(suit).paint(c).to_string()
+}
+
+fn print_card_main_part(card: &Card, is_head: bool) -> Vec<String> {
+    let mut ret = vec![];
+    if is_head {
+        ret.push("╭────────╮ ".to_string());
+    }
+    ret.push(if is_head {
+        match *card {
+            Card::Dragon(s) => format!("│ {}      │ ", ansi_of_dragon(s)),
+            Card::Flower => "│  ~~~~  │ ".to_string(),
+            Card::Number(s, n) => format!("│ {}      │ ", style_of_suit(s).paint(n.to_string()), ),
+        }
+    } else {
+        match *card {
+            Card::Dragon(s) => format!("│      {} │ ", ansi_of_dragon(s)),
+            Card::Flower => "│  ~~~~  │ ".to_string(),
+            Card::Number(s, n) => format!("│      {} │ ", style_of_suit(s).paint(n.to_string()), ),
+        }
+    });
+    if !is_head {
+        ret.push("╰────────╯ ".to_string());
+    }
+    ret
+}
+
+fn empty_column() -> Vec<String> {
+    vec![
+        "           ".to_string(),
+        "           ".to_string(),
+    ]
+}
+
+fn filler_column() -> Vec<String> {
+    vec![
+        "│        │ ".to_string(),
+        "│        │ ".to_string(),
+    ]
+}
+
+fn print_card(card: &Card) -> Vec<String> {
+    let mut ret = vec![];
+    ret.extend(print_card_main_part(card, true));
+    ret.extend(filler_column());
+    ret.extend(filler_column());
+    ret.extend(print_card_main_part(card, false));
+    ret
+}
+
+// The back of the dragons of @suit, in the colour of the suit.
+fn print_flipped_card(suit: Suit) -> Vec<String> {
+    let mut ret = vec![];
+    ret.push("╭────────╮ ".to_string());
+    for _ in 0..6 {
+        ret.push(format!("│{}│ ", style_of_suit(suit).paint("▒▒▒▒▒▒▒▒")));
+    }
+    ret.push("╰────────╯ ".to_string());
+    ret
+}
+
+fn print_free_card() -> Vec<String> {
+    let mut ret = vec![];
+    ret.push("╭────────╮ ".to_string());
+    for _ in 0..6 {
+        ret.push("│        │ ".to_string());
+    }
+    ret.push("╰────────╯ ".to_string());
+    ret
+}
+
+// Something to draw in a bright colour: the slot at @position, or if @cards isn't zero, just that
+// many cards on top of a tableau column.
+#[derive(Debug, Copy, Clone)]
+pub struct Highlight {
+    pub position: Position,
+    pub cards: usize,
+    pub colour: Colour,
+}
+
+// Draws the frame of the card(s) in @lines in @colour.
+fn highlight_frame(lines: &mut [String], colour: Colour) {
+    let style = colour.bold();
+    for line in lines.iter_mut() {
+        *line = line.chars().map(|c| match c {
+            '╭' | '─' | '╮' | '│' | '╰' | '╯' | '┄' | '┆' => style.paint(c.to_string()).to_string(),
+            _ => c.to_string(),
+        }).collect();
+    }
+}
+
+// The flower spot is drawn with a dashed outline while the flower isn't there yet.
+fn print_flower_spot(flower: Option<Card>) -> Vec<String> {
+    let mut ret = match flower {
+        Some(card) => print_card(&card),
+        None => {
+            let mut ret = vec!["╭┄┄┄┄┄┄┄┄╮ ".to_string()];
+            for i in 0..6 {
+                ret.push(if i == 2 {
+                    format!("┆  {}  ┆ ", Style::new().dimmed().paint("~~~~"))
+                } else {
+                    "┆        ┆ ".to_string()
+                });
+            }
+            ret.push("╰┄┄┄┄┄┄┄┄╯ ".to_string());
+            ret
+        }
+    };
+    // Leave some room before the piles.
+    for line in ret.iter_mut() {
+        line.push_str("   ");
+    }
+    ret
+}
+
+// Card drawing: each non-topmost card consists of 1 'head' piece (where 1 piece == 2 lines)
+// and the topmost card consists of 4 pieces (head, 2 filler, tail)
+//╭────────╮\ head
+//│ 2      │/
+//╭────────╮\ head
+//│ 1      │/
+//│        |\ filler 1
+//│        │/
+//│        │\ filler 2
+//│        │/
+//│      1 │\ tail
+//╰────────╯/
+// So for a stack of n cards, we always draw n + 3 pieces.
+// Exception: empty stacks are not drawn.
+
+fn print_labels(labels: &[String]) -> String {
+    labels.iter().map(|label| Style::new().dimmed().paint(label.as_str()).to_string()).collect()
+}
+
+// Puts the columns of lines in @prints side by side.
+fn join_columns(prints: &[Vec<String>]) -> Vec<String> {
+    (0..prints[0].len()).map(|i| prints.iter().map(|column| column[i].as_str()).collect()).collect()
+}
+
+fn print_tableau(playfield: &Playfield, highlights: &[Highlight]) -> Vec<String> {
+    let max_col_height = playfield.tableau().iter().map(|cs| cs.len()).max().unwrap();
+    let mut prints: Vec<Vec<String>> = vec![];
+    for col in 0..8 {
+        let cards_in_column = &playfield.tableau()[col];
+        let column_height = cards_in_column.len();
+
+        let mut column_lines: Vec<String> = vec![];
+
+        for piece_index in 0..(max_col_height + 3) {
+            let is_head = piece_index < column_height;
+            let is_filler = !is_head && (piece_index < column_height + 2 && !cards_in_column.is_empty());
+            let is_tail = piece_index == column_height + 2 && !cards_in_column.is_empty();
+
+            if is_head {
+                column_lines.extend(print_card_main_part(cards_in_column.get(piece_index).unwrap(), true));
+            } else if is_filler {
+                column_lines.extend(filler_column())
+            } else if is_tail {
+                column_lines.extend(print_card_main_part(cards_in_column.get(piece_index - 3).unwrap(), false));
+            } else {
+                column_lines.extend(empty_column());
+            }
+        }
+        for highlight in highlights.iter().filter(|h| h.position == Position::Tableau(col)) {
+            let end = if cards_in_column.is_empty() {
+                // Show the outline of the empty slot, so there's something to highlight.
+                let outline = print_free_card();
+                let len = outline.len().min(column_lines.len());
+                column_lines.splice(..len, outline.into_iter().take(len));
+                len
+            } else {
+                2 * (column_height + 3)
+            };
+            let start = match highlight.cards {
+                0 => 0,
+                cards => 2 * column_height.saturating_sub(cards),
+            };
+            highlight_frame(&mut column_lines[start..end], highlight.colour);
+        }
+        prints.push(column_lines);
+    }
+    join_columns(&prints)
+}
+
+// The dragon buttons light up when the dragons of their suit can be flipped, and go dim once
+// they've been flipped.
+fn dragon_button_style(playfield: &Playfield, suit: Suit) -> Style {
+    if playfield.flipped_suits()[suit.to_index()] {
+        Style::new().dimmed()
+    } else if flip_dragon(playfield.clone(), suit).is_ok() {
+        style_of_suit(suit).bold().reverse()
+    } else {
+        style_of_suit(suit)
+    }
+}
+
+fn print_top(playfield: &Playfield, highlights: &[Highlight]) -> Vec<String> {
+    let mut prints: Vec<Vec<String>> = vec![];
+    let highlighted = |mut lines: Vec<String>, position: Position| {
+        for highlight in highlights.iter().filter(|h| h.position == position) {
+            highlight_frame(&mut lines, highlight.colour);
+        }
+        lines
+    };
+    for (i, fc) in playfield.freecells().iter().enumerate() {
+        let lines = match *fc {
+            FreeCell::InUse(c) => print_card(&c),
+            FreeCell::Flipped(suit) => print_flipped_card(suit),
+            FreeCell::Free => print_free_card(),
+        };
+        prints.push(highlighted(lines, Position::FreeCell(i)));
+    }
+
+    let mut tmp = vec![];
+    // Draw flower & dragon symbols here
+    for i in 0..playfield.flipped_suits().len() {
+        let style = dragon_button_style(playfield, Suit::from_index(i));
+        tmp.extend([
+            "╭─╮",
+            "╰─╯",
+        ].iter().map(|x| format!("  {}   ", style.paint(*x))));
+        tmp.push("        ".to_string());
+    }
+    tmp.pop();
+    prints.push(tmp);
+    prints.push(highlighted(print_flower_spot(playfield.flower()), Position::Flower));
+
+    for (i, p) in playfield.piles().iter().enumerate() {
+        let lines = match *p {
+            Some(c) => print_card(&c),
+            _ => print_free_card(),
+        };
+        prints.push(highlighted(lines, Position::Pile(i)));
+    }
+    join_columns(&prints)
+}
+
+// The names used for the positions in typed commands, so the player doesn't have to count columns.
+fn top_labels() -> Vec<String> {
+    let mut ret: Vec<String> = (1..4).map(|i| format!("{:^10} ", format!("f{}", i))).collect();
+    ret.push("        ".to_string());
+    ret.push(format!("{:^10}    ", "flower"));
+    ret.extend((1..4).map(|i| format!("{:^10} ", format!("p{}", i))));
+    ret
+}
+
+fn tableau_labels() -> Vec<String> {
+    (1..9).map(|i| format!("{:^10} ", format!("t{}", i))).collect()
+}
+
+// Where things are on the screen, see print_top() and print_tableau(): every card takes 10
+// characters plus a space, the dragon buttons and the flower spot are between the free cells and
+// the piles, and the tableau is below the top row and the two rows of labels.
+const CARD_WIDTH: usize = 11;
+const TOP_HEIGHT: usize = 8;
+const FLOWER_LEFT: usize = 3 * CARD_WIDTH + 8;
+const PILES_LEFT: usize = FLOWER_LEFT + 14;
+const TABLEAU_TOP: usize = TOP_HEIGHT + 2;
+
+// Finds what print_playfield() drew at column @x, row @y of the screen. Returns the position and
+// the number of cards from the one drawn there to the top of the stack (0 for empty columns).
+// Clicking below a tableau column counts as clicking its top card.
+pub fn position_at(playfield: &Playfield, x: usize, y: usize) -> Option<(Position, usize)> {
+    if y < TOP_HEIGHT {
+        return if x < 3 * CARD_WIDTH {
+            Some((Position::FreeCell(x / CARD_WIDTH), 1))
+        } else if x < FLOWER_LEFT {
+            None
+        } else if x < PILES_LEFT {
+            Some((Position::Flower, 1))
+        } else if x < PILES_LEFT + 3 * CARD_WIDTH {
+            Some((Position::Pile((x - PILES_LEFT) / CARD_WIDTH), 1))
+        } else {
+            None
+        };
+    }
+    if y < TABLEAU_TOP || x >= 8 * CARD_WIDTH {
+        return None;
+    }
+    let ti = x / CARD_WIDTH;
+    let height = playfield.tableau()[ti].len();
+    // Every card except the topmost one shows only its two-line head.
+    let index = ((y - TABLEAU_TOP) / 2).min(height.saturating_sub(1));
+    Some((Position::Tableau(ti), height - index.min(height)))
+}
+
+// Finds the dragon button drawn at column @x, row @y of the screen. The buttons are stacked
+// between the free cells and the flower spot, three rows apart.
+pub fn dragon_button_at(x: usize, y: usize) -> Option<Suit> {
+    if (3 * CARD_WIDTH..FLOWER_LEFT).contains(&x) && y < TOP_HEIGHT && y % 3 != 2 {
+        Some(Suit::from_index(y / 3))
+    } else {
+        None
+    }
+}
+
+pub fn print_playfield(playfield: &Playfield, highlights: &[Highlight]) -> Vec<String> {
+    let mut lines = print_top(playfield, highlights);
+    lines.push(print_labels(&top_labels()));
+    lines.push(print_labels(&tableau_labels()));
+    lines.extend(print_tableau(playfield, highlights));
+    lines
+}
+
+#[test]
+fn test_render() {
+    let render_test = Playfield::empty()
+        .with_freecell_cards(vec![Card::Dragon(Suit::Black)], FreeCell::Free)
+        .with_freecell(1, FreeCell::Flipped(Suit::Red))
+        .with_piles([Some(Card::Number(Suit::Red, 4)), Some(Card::Number(Suit::Green, 1)), Some(Card::Number(Suit::Black, 9))])
+        .with_tableau_column(1, vec![Card::Number(Suit::Red, 1)])
+        .with_tableau_column(2, vec![Card::Number(Suit::Red, 1), Card::Number(Suit::Black, 2)])
+        .with_tableau_column(7, vec![Card::Number(Suit::Red, 1), Card::Number(Suit::Black, 2), Card::Number(Suit::Green, 3), Card::Number(Suit::Red, 4), Card::Number(Suit::Black, 9), Card::Number(Suit::Black, 8), Card::Number(Suit::Black, 7), Card::Number(Suit::Black, 6), Card::Number(Suit::Black, 5), Card::Number(Suit::Black, 4), Card::Number(Suit::Black, 3), Card::Number(Suit::Black, 2), Card::Number(Suit::Black, 1), ]);
+    let highlights = [
+        Highlight { position: Position::FreeCell(1), cards: 0, colour: Colour::Yellow },
+        Highlight { position: Position::Tableau(2), cards: 1, colour: Colour::Yellow },
+    ];
+    let lines = print_playfield(&render_test, &highlights);
+    let frame = |c: &str| Colour::Yellow.bold().paint(c).to_string();
+    // The top row, the two rows of labels and the tallest column: 13 cards in 16 pieces
+    assert_eq!(lines.len(), TOP_HEIGHT + 2 + 2 * 16);
+
+    // The flipped free cell shows the back of the red dragons in a highlighted frame
+    let text = strip_escapes(&lines);
+    assert!(text[0].starts_with("╭────────╮ ╭────────╮"));
+    assert!(text[1].starts_with("│ =      │ │▒▒▒▒▒▒▒▒│"));
+    assert!(lines[0].contains(&format!("{}{}", frame("╭"), frame("─"))));
+    assert!(lines[1].contains(&format!("{}{}{}", frame("│"), style_of_suit(Suit::Red).paint("▒▒▒▒▒▒▒▒"), frame("│"))));
+
+    // Only the top card of t3 is highlighted, not the one under it
+    let t3 = |line: &str| line.chars().skip(2 * CARD_WIDTH).take(CARD_WIDTH).collect::<String>();
+    assert_eq!(t3(&text[TABLEAU_TOP]), "╭────────╮ ");
+    assert_eq!(t3(&text[TABLEAU_TOP + 2]), "╭────────╮ ");
+    assert!(!lines[TABLEAU_TOP].contains(&frame("╭")));
+    assert!(lines[TABLEAU_TOP + 2].contains(&frame("╭")));
+    assert!(lines[TABLEAU_TOP + 9].contains(&frame("╯")));
+}
+
+// Animations are made of frames of the playfield with the moving cards drawn on top.
+const FRAME_TIME: Duration = Duration::from_millis(25);
+
+// Where the top left corner of a card at @position is drawn, @index being the index of the card in
+// a tableau column.
+fn card_origin(position: Position, index: usize) -> (usize, usize) {
+    match position {
+        Position::FreeCell(i) => (i * CARD_WIDTH, 0),
+        Position::Flower => (FLOWER_LEFT, 0),
+        Position::Pile(i) => (PILES_LEFT + i * CARD_WIDTH, 0),
+        Position::Tableau(i) => (i * CARD_WIDTH, TABLEAU_TOP + 2 * index),
+    }
+}
+
+fn column_len(playfield: &Playfield, position: Position) -> usize {
+    match position {
+        Position::Tableau(i) => playfield.tableau()[i].len(),
+        _ => 0,
+    }
+}
+
+// @cards stacked as in a tableau column, without the space on the right.
+fn print_stack(cards: &[Card]) -> Vec<String> {
+    let (top, rest) = cards.split_last().unwrap();
+    let mut lines: Vec<String> = rest.iter().flat_map(|card| print_card_main_part(card, true)).collect();
+    lines.extend(print_card(top));
+    lines.iter().map(|line| line.trim_end_matches(' ').to_string()).collect()
+}
+
+// A card seen at an angle while it's being turned over: @width characters wide and centred, with
+// the back of the dragons of @back showing if it's given.
+fn print_turning_card(width: usize, back: Option<Suit>) -> Vec<String> {
+    let pad = " ".repeat((CARD_WIDTH - 1 - width) / 2);
+    let inner = match back {
+        Some(suit) => style_of_suit(suit).paint("▒".repeat(width - 2)).to_string(),
+        None => " ".repeat(width - 2),
+    };
+    let mut ret = vec![format!("{}╭{}╮{}", pad, "─".repeat(width - 2), pad)];
+    for _ in 0..6 {
+        ret.push(format!("{}│{}│{}", pad, inner, pad));
+    }
+    ret.push(format!("{}╰{}╯{}", pad, "─".repeat(width - 2), pad));
+    ret
+}
+
+// Draws each of @sprites over @lines, with its top left corner at the given column and line.
+fn draw_sprites(mut lines: Vec<String>, sprites: &[(&[String], (usize, usize))]) -> Vec<String> {
+    for &(sprite, (x, y)) in sprites {
+        for (i, sprite_line) in sprite.iter().enumerate() {
+            if lines.len() <= y + i {
+                lines.resize(y + i + 1, String::new());
+            }
+            lines[y + i] = overlay(&lines[y + i], x, sprite_line);
+        }
+    }
+    lines
+}
+
+// The number of frames for sliding a card from @from to @to: the longer the way, the more frames.
+fn slide_frames(from: (usize, usize), to: (usize, usize)) -> usize {
+    // Lines are about twice as high as columns are wide.
+    let distance = from.0.abs_diff(to.0) + 2 * from.1.abs_diff(to.1);
+    (distance / 8).clamp(3, 12)
+}
+
+// The point at @t (from 0 to 1) of the way from @from to @to.
+fn slide_point(from: (usize, usize), to: (usize, usize), t: f64) -> (usize, usize) {
+    let mix = |a: usize, b: usize| (a as f64 + (b as f64 - a as f64) * t).round() as usize;
+    (mix(from.0, to.0), mix(from.1, to.1))
+}
+
+// The frames of the playfield for animating @action made on @playfield, up to but not including
+// the playfield after it.
+fn animation_frames(playfield: &Playfield, action: Action) -> Vec<Vec<String>> {
+    match action {
+        Action::Move(Move(count, from, to)) => {
+            let (base, cards) = match pick_up_cards(playfield.clone(), count, from) {
+                Ok(picked_up) => picked_up,
+                Err(_) => return vec![],
+            };
+            let start = card_origin(from, column_len(playfield, from) - column_len(playfield, from).min(count));
+            let end = card_origin(to, column_len(&base, to));
+            let sprite = print_stack(&cards);
+            let background = print_playfield(&base, &[]);
+            let frames = slide_frames(start, end);
+            (1..frames + 1)
+                .map(|i| draw_sprites(background.clone(), &[(&sprite, slide_point(start, end, i as f64 / frames as f64))]))
+                .collect()
+        }
+        Action::FlipDragon(suit) => {
+            let after = match flip_dragon(playfield.clone(), suit) {
+                Ok(after) => after,
+                Err(_) => return vec![],
+            };
+            let cell = match (0..after.freecells().len())
+                .find(|&i| after.freecells()[i] == FreeCell::Flipped(suit)) {
+                Some(cell) => cell,
+                None => return vec![],
+            };
+            // The dragons all slide to the free cell, where they're turned over.
+            let base = after.with_freecell(cell, FreeCell::Free);
+            let dragon = Card::Dragon(suit);
+            let starts: Vec<(usize, usize)> = (0..playfield.freecells().len())
+                .filter(|&i| playfield.freecells()[i] == FreeCell::InUse(dragon))
+                .map(|i| card_origin(Position::FreeCell(i), 0))
+                .chain((0..playfield.tableau().len())
+                    .filter(|&i| playfield.tableau()[i].last() == Some(&dragon))
+                    .map(|i| card_origin(Position::Tableau(i), playfield.tableau()[i].len() - 1)))
+                .collect();
+            let end = card_origin(Position::FreeCell(cell), 0);
+            let sprite = print_stack(&[dragon]);
+            let background = print_playfield(&base, &[]);
+            let frames = starts.iter().map(|&start| slide_frames(start, end)).max().unwrap_or(0);
+            let mut ret: Vec<Vec<String>> = (1..frames + 1).map(|i| {
+                let t = i as f64 / frames as f64;
+                let sprites: Vec<(&[String], (usize, usize))> = starts.iter()
+                    .map(|&start| (&sprite[..], slide_point(start, end, t)))
+                    .collect();
+                draw_sprites(background.clone(), &sprites)
+            }).collect();
+            for &(width, back) in &[(6, None), (2, None), (6, Some(suit))] {
+                ret.push(draw_sprites(background.clone(), &[(&print_turning_card(width, back), end)]));
+            }
+            ret
+        }
+    }
+}
+
+// Shows @steps being made one after another, starting from @playfield, with @footer below the
+// playfield.
+pub fn animate_steps(screen: &mut Screen, playfield: &Playfield, steps: &[Step], footer: &[String]) {
+    let mut playfield = playfield.clone();
+    for step in steps {
+        for mut frame in animation_frames(&playfield, step.action) {
+            frame.extend(footer.iter().cloned());
+            screen.draw(frame);
+            thread::sleep(FRAME_TIME);
+        }
+        playfield = match apply_action(playfield, step.action) {
+            Ok(playfield) => playfield,
+            Err(_) => return,
+        };
+    }
+}
+
+#[test]
+fn test_position_at() {
+    let pf = Playfield::empty()
+        .with_tableau_column(1, vec![Card::Dragon(Suit::Red), Card::Number(Suit::Red, 7), Card::Number(Suit::Black, 6)]);
+    assert_eq!(position_at(&pf, 0, 0), Some((Position::FreeCell(0), 1)));
+    assert_eq!(position_at(&pf, 32, 7), Some((Position::FreeCell(2), 1)));
+    assert_eq!(position_at(&pf, 35, 3), None);
+    assert_eq!(position_at(&pf, 45, 3), Some((Position::Flower, 1)));
+    assert_eq!(position_at(&pf, 60, 3), Some((Position::Pile(0), 1)));
+    assert_eq!(position_at(&pf, 87, 3), Some((Position::Pile(2), 1)));
+    assert_eq!(position_at(&pf, 88, 3), None);
+    assert_eq!(position_at(&pf, 5, 8), None);
+    assert_eq!(dragon_button_at(35, 0), Some(Suit::Red));
+    assert_eq!(dragon_button_at(36, 4), Some(Suit::Green));
+    assert_eq!(dragon_button_at(36, 5), None);
+    assert_eq!(dragon_button_at(40, 7), Some(Suit::Black));
+    assert_eq!(dragon_button_at(41, 7), None);
+
+    // Column t2: the heads of the dragon and Red 7 are on rows 10-13, Black 6 is drawn fully below
+    assert_eq!(position_at(&pf, 12, 10), Some((Position::Tableau(1), 3)));
+    assert_eq!(position_at(&pf, 12, 13), Some((Position::Tableau(1), 2)));
+    assert_eq!(position_at(&pf, 12, 14), Some((Position::Tableau(1), 1)));
+    assert_eq!(position_at(&pf, 12, 30), Some((Position::Tableau(1), 1)));
+    assert_eq!(position_at(&pf, 0, 12), Some((Position::Tableau(0), 0)));
+}
+
+#[test]
+fn test_dragon_button_style() {
+    // All four red dragons are exposed, one of them in a free cell
+    let exposed = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Dragon(Suit::Red)])
+        .with_tableau_column(1, vec![Card::Dragon(Suit::Red)])
+        .with_tableau_column(3, vec![Card::Dragon(Suit::Red)])
+        .with_freecell_cards(vec![Card::Dragon(Suit::Red)], FreeCell::Free);
+    assert_eq!(dragon_button_style(&exposed, Suit::Red), style_of_suit(Suit::Red).bold().reverse());
+    assert_eq!(dragon_button_style(&exposed, Suit::Green), style_of_suit(Suit::Green));
+    // One of them buried under another card
+    let buried = exposed.with_tableau_column(3, vec![Card::Dragon(Suit::Red), Card::Number(Suit::Black, 5)]);
+    assert_eq!(dragon_button_style(&buried, Suit::Red), style_of_suit(Suit::Red));
+
+    let flipped = flip_dragon(exposed, Suit::Red).unwrap();
+    assert_eq!(dragon_button_style(&flipped, Suit::Red), Style::new().dimmed());
+    assert_eq!(dragon_button_style(&flipped, Suit::Black), style_of_suit(Suit::Black));
+}
+
+#[test]
+fn test_flower_spot() {
+    let flower_spot = |playfield: &Playfield| -> Vec<String> {
+        strip_escapes(&print_top(playfield, &[])).iter()
+            .map(|line| line.chars().skip(FLOWER_LEFT).take(PILES_LEFT - FLOWER_LEFT).collect())
+            .collect()
+    };
+    // Dashed until the flower is there, with a faint flower in the middle
+    let empty = Playfield::empty();
+    assert_eq!(flower_spot(&empty), vec![
+        "╭┄┄┄┄┄┄┄┄╮    ", "┆        ┆    ", "┆        ┆    ", "┆  ~~~~  ┆    ",
+        "┆        ┆    ", "┆        ┆    ", "┆        ┆    ", "╰┄┄┄┄┄┄┄┄╯    ",
+    ]);
+    assert!(print_top(&empty, &[])[3].contains(&Style::new().dimmed().paint("~~~~").to_string()));
+
+    let placed = apply_move(Playfield::empty().with_tableau_column(0, vec![Card::Flower]),
+                            Move(1, Position::Tableau(0), Position::Flower)).unwrap();
+    assert_eq!(placed.flower(), Some(Card::Flower));
+    assert_eq!(flower_spot(&placed), vec![
+        "╭────────╮    ", "│  ~~~~  │    ", "│        │    ", "│        │    ",
+        "│        │    ", "│        │    ", "│  ~~~~  │    ", "╰────────╯    ",
+    ]);
+}
+
+#[cfg(test)]
+fn strip_escapes(lines: &[String]) -> Vec<String> {
+    lines.iter().map(|line| {
+        let mut text = String::new();
+        let mut rest = line.as_str();
+        while let Some(start) = rest.find('\x1b') {
+            text.push_str(&rest[..start]);
+            rest = &rest[start..];
+            rest = &rest[rest.find('m').unwrap() + 1..];
+        }
+        text.push_str(rest);
+        text.trim_end().to_string()
+    }).collect()
+}
+
+#[test]
+fn test_animation_frames() {
+    let pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Dragon(Suit::Red), Card::Number(Suit::Green, 7), Card::Number(Suit::Red, 6)])
+        .with_tableau_column(1, vec![Card::Dragon(Suit::Red)])
+        .with_tableau_column(3, vec![Card::Dragon(Suit::Red)])
+        .with_freecell_cards(vec![Card::Dragon(Suit::Red)], FreeCell::Free);
+    // The cards end up where they're drawn after the move
+    let m = Move(2, Position::Tableau(0), Position::Tableau(2));
+    let frames = animation_frames(&pf, Action::Move(m));
+    assert!(frames.len() >= 3);
+    assert_eq!(strip_escapes(frames.last().unwrap()), strip_escapes(&print_playfield(&apply_move(pf.clone(), m).unwrap(), &[])));
+    assert!(animation_frames(&pf, Action::Move(Move(1, Position::Tableau(4), Position::Tableau(2)))).is_empty());
+
+    let pf = apply_move(pf, Move(2, Position::Tableau(0), Position::Tableau(2))).unwrap();
+    let frames = animation_frames(&pf, Action::FlipDragon(Suit::Red));
+    // The dragons slide to the last free cell, the one in t1 having the longest way, and then turn over
+    let slide = slide_frames(card_origin(Position::Tableau(0), 0), card_origin(Position::FreeCell(2), 0));
+    assert_eq!(frames.len(), slide + 3);
+    assert!(frames.last().unwrap()[3].contains(&format!("│{}│", style_of_suit(Suit::Red).paint("▒▒▒▒"))));
+}
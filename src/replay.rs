@@ -0,0 +1,163 @@
+// Replaying saved games and the solutions of deals.
+use ansi_term::{Colour, Style};
+use game_logic::*;
+use game_state::*;
+use savefile::*;
+use solver::*;
+use game::*;
+use render::*;
+use terminal::*;
+use std::time::Duration;
+
+const REPLAY_KEYS_HELP: &str = "\
+Right/n: next move  Left/p: previous move  g: go to move  Space: play/pause  +/-: faster/slower  q: quit";
+
+// The fastest and slowest autoplay speeds.
+pub const MIN_REPLAY_DELAY: Duration = Duration::from_millis(50);
+pub const MAX_REPLAY_DELAY: Duration = Duration::from_secs(10);
+
+fn last_move(state: &GameState) -> Option<Action> {
+    state.steps().iter().rev().find(|s| !s.automatic).map(|step| step.action)
+}
+
+fn replay_status_lines(state: &GameState, delay: Duration, playing: bool, message: Option<&String>) -> Vec<String> {
+    let speed = if playing { format!("Playing, {:.2}s per move", delay.as_secs_f64()) } else { "Paused".to_string() };
+    let mut lines = vec![String::new(), Style::new().reverse().paint(format!(
+        " Replay of {}  Move: {}/{}  Step: {}/{}  {} ", game_title(state), state.moves(), state.history_moves(),
+        state.position(), state.history_len(), speed)).to_string()];
+    lines.push(Style::new().dimmed().paint(REPLAY_KEYS_HELP).to_string());
+    match (message, last_move(state)) {
+        (Some(_), _) => lines.extend(message_lines(message)),
+        (None, Some(action)) => lines.push(format!("Last move: {}", format_action(action))),
+        (None, None) => {}
+    }
+    lines
+}
+
+// The positions of @playfield changed by @action, which has just been made. The dragons of a flip
+// are gone by then, so it's the free cell they were flipped to.
+fn made_action_positions(playfield: &Playfield, action: Action) -> Vec<Position> {
+    match action {
+        Action::Move(_) => action_positions(playfield, action),
+        Action::FlipDragon(suit) => (0..playfield.freecells().len())
+            .filter(|&fi| playfield.freecells()[fi] == FreeCell::Flipped(suit))
+            .map(Position::FreeCell)
+            .collect(),
+    }
+}
+
+fn draw_replay(screen: &mut Screen, state: &GameState, delay: Duration, playing: bool, message: Option<&String>) {
+    // The cards moved by the last action are highlighted.
+    let highlights: Vec<Highlight> = last_move(state)
+        .map_or(vec![], |action| made_action_positions(state.playfield(), action))
+        .into_iter()
+        .map(|position| Highlight { position, cards: 0, colour: Colour::Yellow })
+        .collect();
+    let mut lines = print_playfield(state.playfield(), &highlights);
+    lines.extend(replay_status_lines(state, delay, playing, message));
+    screen.draw(lines);
+}
+
+// Goes to the next move of the replay of @state, animating it if @animate is set. Returns false at
+// the end of the game.
+fn replay_forward(screen: &mut Screen, state: &mut GameState, animate: bool, footer: &[String]) -> bool {
+    let before = state.playfield().clone();
+    let position = state.position();
+    if !state.redo() {
+        return false;
+    }
+    if animate {
+        animate_steps(screen, &before, &state.steps()[position..], footer);
+    }
+    true
+}
+
+// Steps through the history of @state, from the beginning. When playing, the next move is made
+// every @delay. The moves are animated if @animate is set.
+pub fn replay(mut state: GameState, mut delay: Duration, animate: bool) {
+    let _terminal = enable_raw_terminal();
+    let mut keys = KeyReader::new();
+    let mut screen = Screen::new();
+    state.jump_to_move(0);
+    let mut playing = false;
+    let mut message: Option<String> = None;
+    loop {
+        draw_replay(&mut screen, &state, delay, playing, message.as_ref());
+        message = None;
+        let footer = replay_status_lines(&state, delay, playing, None);
+        let key = match keys.read_key(if playing { Some(delay) } else { None }) {
+            Ok(Some(key)) => key,
+            Ok(None) => {
+                playing = replay_forward(&mut screen, &mut state, animate, &footer)
+                    && state.position() < state.history_len();
+                continue;
+            }
+            Err(_) => break,
+        };
+        match key {
+            Key::Right | Key::Char('n') => {
+                let moved = replay_forward(&mut screen, &mut state, animate, &footer);
+                message = if moved { None } else { Some("This is the end of the game".to_string()) };
+            }
+            Key::Left | Key::Char('p') =>
+                message = if state.undo() { None } else { Some("This is the beginning of the game".to_string()) },
+            Key::Char(' ') => {
+                // Playing from the end starts over.
+                if !playing && state.position() == state.history_len() {
+                    state.jump_to_move(0);
+                }
+                playing = !playing;
+            }
+            Key::Char('+') => delay = (delay / 2).max(MIN_REPLAY_DELAY),
+            Key::Char('-') => delay = (delay * 2).min(MAX_REPLAY_DELAY),
+            Key::Char('g') => {
+                match read_command_line(&mut keys, "Go to move: ") {
+                    Ok(Some(line)) => match line.trim().parse() {
+                        Ok(moves) if state.jump_to_move(moves) => {}
+                        _ => message = Some(format!("There's no move '{}', the game has {} moves",
+                                                    line.trim(), state.history_moves())),
+                    },
+                    Ok(None) => {}
+                    Err(_) => break,
+                }
+            }
+            Key::Char('q') | Key::Escape | Key::Interrupt => break,
+            _ => {}
+        }
+    }
+}
+
+// A game of the solver's solution from the start of @game, for replaying.
+pub fn solution_game(game: &GameState) -> Result<GameState, String> {
+    let mut solution = match game.deal() {
+        Some(deal) => GameState::from_deal(deal),
+        None => GameState::new(game.initial().clone()),
+    };
+    println!("Solving...");
+    match find_solution(solution.playfield(), SearchLimits::default()).result {
+        SolveResult::Solved(steps) => {
+            for step in steps.iter().filter(|s| !s.automatic) {
+                solution.apply(step.action).unwrap();
+            }
+            Ok(solution)
+        }
+        SolveResult::Unsolvable => Err("This game can't be won".to_string()),
+        SolveResult::GaveUp => Err("The solver couldn't find a solution".to_string()),
+    }
+}
+
+#[test]
+fn test_made_action_positions() {
+    let pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Dragon(Suit::Red)])
+        .with_tableau_column(1, vec![Card::Dragon(Suit::Red)])
+        .with_tableau_column(3, vec![Card::Dragon(Suit::Red)])
+        .with_freecell_cards(vec![Card::Dragon(Suit::Red)], FreeCell::Free);
+    assert_eq!(action_positions(&pf, Action::FlipDragon(Suit::Red)),
+               vec![Position::FreeCell(0), Position::Tableau(0), Position::Tableau(1), Position::Tableau(3)]);
+    // After the flip the dragons are all in the last free cell
+    let flipped = flip_dragon(pf, Suit::Red).unwrap();
+    assert_eq!(made_action_positions(&flipped, Action::FlipDragon(Suit::Red)), vec![Position::FreeCell(2)]);
+    let m = Move(1, Position::Tableau(0), Position::Tableau(2));
+    assert_eq!(made_action_positions(&flipped, Action::Move(m)), vec![Position::Tableau(0), Position::Tableau(2)]);
+}
@@ -0,0 +1,446 @@
+use game_logic::*;
+use game_state::*;
+use std::fs::File;
+use std::io::{Read, Write};
+
+// Human-readable text formats for playfields and for whole games.
+//
+// Cards are written as two characters: the suit (R, G or B) followed by the number or D for a
+// dragon, e.g. "R5" or "GD". The flower is "FL". A board looks like this:
+//
+//   shenzen-solitaire board
+//   freecells: GD -- B#
+//   flipped: black
+//   flower: FL
+//   piles: R3 -- B2
+//   t1: R5 GD B4
+//   t2:
+//   ...
+//   t8: G9 G8
+//
+// where "--" is an empty spot, "B#" a free cell holding the flipped black dragons and the tableau
+// columns are listed from the bottom card to the top card. Older files have "##" for a flipped free
+// cell without its suit; those can still be read if only one suit is flipped, as the suit is then
+// known. With more suits flipped, the suits must be filled in by hand.
+//
+// A game is the deal it started from followed by the player's moves, written as by format_action()
+// (the automatic moves are redone when loading):
+//
+//   shenzen-solitaire game
+//   deal: 1234
+//   moves:
+//   t3 f1
+//   dragon red
+//
+// Instead of the "deal:" line, a game that didn't start from a numbered deal has "board:"
+// followed by the board lines.
+
+const BOARD_HEADER: &str = "shenzen-solitaire board";
+const GAME_HEADER: &str = "shenzen-solitaire game";
+
+const SUITS: [Suit; 3] = [Suit::Red, Suit::Green, Suit::Black];
+
+fn suit_letter(suit: Suit) -> char {
+    match suit {
+        Suit::Red => 'R',
+        Suit::Green => 'G',
+        Suit::Black => 'B',
+    }
+}
+
+pub fn format_card(card: Card) -> String {
+    match card {
+        Card::Number(suit, number) => format!("{}{}", suit_letter(suit), number),
+        Card::Dragon(suit) => format!("{}D", suit_letter(suit)),
+        Card::Flower => "FL".to_string(),
+    }
+}
+
+fn parse_suit_letter(c: Option<char>) -> Option<Suit> {
+    match c {
+        Some('R') => Some(Suit::Red),
+        Some('G') => Some(Suit::Green),
+        Some('B') => Some(Suit::Black),
+        _ => None,
+    }
+}
+
+pub fn parse_card(s: &str) -> Result<Card, String> {
+    if s == "FL" {
+        return Ok(Card::Flower);
+    }
+    let mut chars = s.chars();
+    let suit = parse_suit_letter(chars.next()).ok_or(format!("Invalid card '{}'", s))?;
+    match (chars.next(), chars.next()) {
+        (Some('D'), None) => Ok(Card::Dragon(suit)),
+        (Some(c @ '1'..='9'), None) => Ok(Card::Number(suit, c.to_digit(10).unwrap() as usize)),
+        _ => Err(format!("Invalid card '{}'", s)),
+    }
+}
+
+pub fn parse_suit(s: &str) -> Result<Suit, String> {
+    match s {
+        "r" | "red" => Ok(Suit::Red),
+        "g" | "green" => Ok(Suit::Green),
+        "b" | "black" => Ok(Suit::Black),
+        _ => Err(format!("Unknown suit '{}' (expected red, green or black)", s)),
+    }
+}
+
+/// Positions are written as a letter followed by a 1-based index, e.g. "t1" for the leftmost
+/// tableau column.
+pub fn parse_position(s: &str) -> Result<Position, String> {
+    if s == "flower" {
+        return Ok(Position::Flower);
+    }
+    let (kind, max) = match s.chars().next() {
+        Some('f') => ("free cell", 3),
+        Some('p') => ("pile", 3),
+        Some('t') => ("tableau column", 8),
+        _ => return Err(format!("Unknown position '{}'", s)),
+    };
+    let index = match s[1..].parse::<usize>() {
+        Ok(i) if i >= 1 && i <= max => i - 1,
+        _ => return Err(format!("There is no {} '{}' (expected 1 to {})", kind, &s[1..], max)),
+    };
+    Ok(match kind {
+        "free cell" => Position::FreeCell(index),
+        "pile" => Position::Pile(index),
+        _ => Position::Tableau(index),
+    })
+}
+
+pub fn format_position(position: Position) -> String {
+    match position {
+        Position::FreeCell(i) => format!("f{}", i + 1),
+        Position::Flower => "flower".to_string(),
+        Position::Pile(i) => format!("p{}", i + 1),
+        Position::Tableau(i) => format!("t{}", i + 1),
+    }
+}
+
+/// The inverse of parse_action().
+pub fn format_action(action: Action) -> String {
+    match action {
+        Action::Move(Move(1, from, to)) => format!("{} {}", format_position(from), format_position(to)),
+        Action::Move(Move(count, from, to)) =>
+            format!("{} {} {}", count, format_position(from), format_position(to)),
+        Action::FlipDragon(suit) => format!("dragon {}", format!("{:?}", suit).to_lowercase()),
+    }
+}
+
+/// Parses a move or a dragon flip written as by format_action(), e.g. "t3 f1", "3 t2 t5" or
+/// "dragon red". Upper case is accepted too.
+pub fn parse_action(s: &str) -> Result<Action, String> {
+    let lowercase = s.to_lowercase();
+    let words: Vec<&str> = lowercase.split_whitespace().collect();
+    match words.as_slice() {
+        ["dragon", suit] | ["d", suit] => Ok(Action::FlipDragon(parse_suit(suit)?)),
+        [from, to] => Ok(Action::Move(Move(1, parse_position(from)?, parse_position(to)?))),
+        [count, from, to] => {
+            let count = match count.parse::<usize>() {
+                Ok(n) if n > 0 => n,
+                _ => return Err(format!("Invalid card count '{}'", count)),
+            };
+            Ok(Action::Move(Move(count, parse_position(from)?, parse_position(to)?)))
+        }
+        _ => Err(format!("Invalid move '{}'", s.trim())),
+    }
+}
+
+fn format_spot(card: Option<Card>) -> String {
+    card.map_or("--".to_string(), format_card)
+}
+
+fn parse_spot(s: &str) -> Result<Option<Card>, String> {
+    if s == "--" { Ok(None) } else { parse_card(s).map(Some) }
+}
+
+fn format_freecell(fc: FreeCell) -> String {
+    match fc {
+        FreeCell::Free => "--".to_string(),
+        FreeCell::InUse(card) => format_card(card),
+        FreeCell::Flipped(suit) => format!("{}#", suit_letter(suit)),
+    }
+}
+
+fn parse_freecell(s: &str) -> Result<FreeCell, String> {
+    match s.strip_suffix('#') {
+        Some(letter) if letter.len() == 1 => parse_suit_letter(letter.chars().next())
+            .map(FreeCell::Flipped)
+            .ok_or(format!("Invalid free cell '{}'", s)),
+        _ => Ok(parse_spot(s)?.map_or(FreeCell::Free, FreeCell::InUse)),
+    }
+}
+
+pub fn format_playfield(playfield: &Playfield) -> String {
+    let mut ret = String::new();
+    let freecells: Vec<String> = playfield.freecells.iter().map(|&fc| format_freecell(fc)).collect();
+    ret += &format!("freecells: {}\n", freecells.join(" "));
+    let flipped: Vec<String> = SUITS.iter()
+        .filter(|suit| playfield.flipped_suits[suit.to_index()])
+        .map(|suit| format!("{:?}", suit).to_lowercase())
+        .collect();
+    ret += format!("flipped: {}", flipped.join(" ")).trim_end();
+    ret += "\n";
+    ret += &format!("flower: {}\n", format_spot(playfield.flower));
+    let piles: Vec<String> = playfield.piles.iter().map(|&p| format_spot(p)).collect();
+    ret += &format!("piles: {}\n", piles.join(" "));
+    for (i, col) in playfield.tableau.iter().enumerate() {
+        let cards: Vec<String> = col.iter().map(|&c| format_card(c)).collect();
+        ret += format!("t{}: {}", i + 1, cards.join(" ")).trim_end();
+        ret += "\n";
+    }
+    ret
+}
+
+// Parses the board lines (without the header).
+fn parse_board_lines<'a, I: Iterator<Item = &'a str>>(lines: I) -> Result<Playfield, String> {
+    let mut pf = Playfield::empty();
+    let mut seen: Vec<String> = vec![];
+    // The free cells written as "##", whose suit isn't known until the flipped suits are.
+    let mut unknown_flipped: Vec<usize> = vec![];
+    for line in lines {
+        let (key, value) = match line.find(':') {
+            Some(idx) => (line[..idx].trim(), line[idx + 1..].trim()),
+            None => return Err(format!("Invalid line '{}'", line)),
+        };
+        if seen.iter().any(|k| k == key) {
+            return Err(format!("'{}' given twice", key));
+        }
+        seen.push(key.to_string());
+        let words: Vec<&str> = value.split_whitespace().collect();
+        match key {
+            "freecells" => {
+                if words.len() != 3 {
+                    return Err("There must be exactly 3 free cells".to_string());
+                }
+                for (i, word) in words.iter().enumerate() {
+                    if *word == "##" {
+                        unknown_flipped.push(i);
+                    } else {
+                        pf.freecells[i] = parse_freecell(word)?;
+                    }
+                }
+            }
+            "flipped" => {
+                for word in words {
+                    pf.flipped_suits[parse_suit(word)?.to_index()] = true;
+                }
+            }
+            "flower" => {
+                pf.flower = match words.as_slice() {
+                    [] | ["--"] => None,
+                    ["FL"] => Some(Card::Flower),
+                    _ => return Err(format!("Invalid flower spot '{}'", value)),
+                };
+            }
+            "piles" => {
+                if words.len() != 3 {
+                    return Err("There must be exactly 3 piles".to_string());
+                }
+                for (i, word) in words.iter().enumerate() {
+                    pf.piles[i] = match parse_spot(word)? {
+                        Some(Card::Dragon(_)) | Some(Card::Flower) =>
+                            return Err(format!("Only numbered cards can be on the piles, not {}", word)),
+                        spot => spot,
+                    };
+                }
+            }
+            _ => match parse_position(key) {
+                Ok(Position::Tableau(ti)) => {
+                    pf.tableau[ti] = words.iter().map(|w| parse_card(w)).collect::<Result<Vec<Card>, String>>()?;
+                }
+                _ => return Err(format!("Unknown key '{}'", key)),
+            },
+        }
+    }
+    if seen.len() != 4 + pf.tableau.len() {
+        return Err("The board is incomplete".to_string());
+    }
+    if !unknown_flipped.is_empty() {
+        let suits: Vec<Suit> = SUITS.iter().cloned().filter(|suit| pf.flipped_suits[suit.to_index()]).collect();
+        match suits.as_slice() {
+            [suit] => {
+                for &fi in &unknown_flipped {
+                    pf.freecells[fi] = FreeCell::Flipped(*suit);
+                }
+            }
+            _ => return Err("Can't tell the suit of a flipped free cell written as '##', write it as e.g. 'R#'".to_string()),
+        }
+    }
+    if let Err(errors) = pf.validate() {
+        let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        return Err(format!("Invalid board: {}", errors.join(", ")));
+    }
+    Ok(pf)
+}
+
+fn content_lines(text: &str) -> Vec<&str> {
+    text.lines().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with('#')).collect()
+}
+
+pub fn parse_playfield(text: &str) -> Result<Playfield, String> {
+    let lines = content_lines(text);
+    match lines.split_first() {
+        Some((&BOARD_HEADER, rest)) => parse_board_lines(rest.iter().cloned()),
+        _ => Err(format!("Not a board, the first line must be '{}'", BOARD_HEADER)),
+    }
+}
+
+pub fn format_board_file(playfield: &Playfield) -> String {
+    format!("{}\n{}", BOARD_HEADER, format_playfield(playfield))
+}
+
+pub fn format_game(state: &GameState) -> String {
+    let mut ret = format!("{}\n", GAME_HEADER);
+    match state.deal() {
+        Some(deal) => ret += &format!("deal: {}\n", deal),
+        None => ret += &format!("board:\n{}", format_playfield(state.initial())),
+    }
+    ret += "moves:\n";
+    for step in state.steps().iter().filter(|s| !s.automatic) {
+        ret += &format_action(step.action);
+        ret += "\n";
+    }
+    ret
+}
+
+pub fn parse_game(text: &str) -> Result<GameState, String> {
+    let lines = content_lines(text);
+    let rest = match lines.split_first() {
+        Some((&GAME_HEADER, rest)) => rest,
+        _ => return Err(format!("Not a saved game, the first line must be '{}'", GAME_HEADER)),
+    };
+    let moves_idx = rest.iter().position(|&l| l == "moves:").ok_or("The list of moves is missing")?;
+    let mut state = match rest[..moves_idx].split_first() {
+        Some((&"board:", board)) => GameState::new(parse_board_lines(board.iter().cloned())?),
+        Some((line, [])) if line.starts_with("deal:") => {
+            let deal = line["deal:".len()..].trim();
+            GameState::from_deal(deal.parse().map_err(|_| format!("Invalid deal number '{}'", deal))?)
+        }
+        _ => return Err("Either the deal number or the board must be given".to_string()),
+    };
+    for (i, line) in rest[moves_idx + 1..].iter().enumerate() {
+        let action = parse_action(line).map_err(|_| format!("Move {} '{}' is invalid", i + 1, line))?;
+        if state.apply(action).is_err() {
+            return Err(format!("Move {} '{}' is illegal", i + 1, line));
+        }
+    }
+    Ok(state)
+}
+
+pub fn save_to_file(path: &str, contents: &str) -> Result<(), String> {
+    File::create(path)
+        .and_then(|mut f| f.write_all(contents.as_bytes()))
+        .map_err(|e| format!("Can't write {}: {}", path, e))
+}
+
+/// Loads either a saved game or a board. A board starts a new game from that position.
+pub fn load_file(path: &str) -> Result<GameState, String> {
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut text))
+        .map_err(|e| format!("Can't read {}: {}", path, e))?;
+    if content_lines(&text).first() == Some(&BOARD_HEADER) {
+        parse_playfield(&text).map(GameState::new)
+    } else {
+        parse_game(&text)
+    }
+}
+
+#[test]
+fn test_card_notation() {
+    for card in make_deck() {
+        assert_eq!(parse_card(&format_card(card)), Ok(card));
+    }
+    assert_eq!(format_card(Card::Number(Suit::Green, 7)), "G7");
+    assert_eq!(format_card(Card::Dragon(Suit::Black)), "BD");
+    assert!(parse_card("R0").is_err());
+    assert!(parse_card("X5").is_err());
+    assert!(parse_card("R55").is_err());
+    assert!(parse_card("").is_err());
+}
+
+// Plays a few moves in deal @deal, avoiding any moves onto the piles.
+#[cfg(test)]
+fn make_test_game(deal: u64, moves: usize) -> GameState {
+    let mut state = GameState::from_deal(deal);
+    for _ in 0..moves {
+        let action = state.playfield().legal_moves().into_iter()
+            .find(|&a| !matches!(a, Action::Move(Move(_, _, Position::Pile(_)))))
+            .unwrap();
+        assert!(state.apply(action).is_ok());
+    }
+    state
+}
+
+#[test]
+fn test_playfield_roundtrip() {
+    let state = make_test_game(3, 3);
+    let text = format_board_file(state.playfield());
+    assert_eq!(parse_playfield(&text).as_ref(), Ok(state.playfield()));
+
+    let mut flipped = Playfield::empty();
+    flipped.freecells = [FreeCell::Flipped(Suit::Green), FreeCell::Free, FreeCell::InUse(Card::Flower)];
+    flipped.flipped_suits = [false, true, false];
+    flipped.piles = [Some(Card::Number(Suit::Red, 9)), Some(Card::Number(Suit::Green, 9)), Some(Card::Number(Suit::Black, 8))];
+    flipped.tableau[2] = vec![Card::Dragon(Suit::Red); 4];
+    flipped.tableau[5] = vec![Card::Dragon(Suit::Black), Card::Dragon(Suit::Black), Card::Number(Suit::Black, 9)];
+    flipped.tableau[7] = vec![Card::Dragon(Suit::Black), Card::Dragon(Suit::Black)];
+    let text = format_board_file(&flipped);
+    assert!(text.contains("freecells: G# -- FL\nflipped: green\nflower: --\npiles: R9 G9 B8\n"));
+    // The older format without the suit of the flipped free cell
+    assert_eq!(parse_playfield(&text.replace("G#", "##")).as_ref(), Ok(&flipped));
+    assert_eq!(parse_playfield(&text), Ok(flipped));
+}
+
+#[test]
+fn test_parse_playfield_rejects_invalid() {
+    let good = format_board_file(&make_shuffled_playfield_from_seed(1));
+    assert!(parse_playfield(&good).is_ok());
+    // Missing header, missing line, duplicate line
+    assert!(parse_playfield(&good.replace(BOARD_HEADER, "")).is_err());
+    assert!(parse_playfield(&good.replace("flower: --\n", "")).is_err());
+    assert!(parse_playfield(&good.replace("flower: --\n", "flower: --\nflower: --\n")).is_err());
+    // A card changed into another one
+    let first_card = &good[good.find("t1: ").unwrap() + 4..][..2];
+    let other = if first_card == "R1" { "R2" } else { "R1" };
+    assert!(parse_playfield(&good.replacen(&format!("t1: {}", first_card), &format!("t1: {}", other), 1)).is_err());
+    // A flipped free cell without a flipped suit
+    assert!(parse_playfield(&good.replace("freecells: -- -- --", "freecells: R# -- --")).is_err());
+}
+
+#[test]
+fn test_format_action_roundtrip() {
+    let actions = [
+        Action::Move(Move(1, Position::Tableau(7), Position::FreeCell(2))),
+        Action::Move(Move(4, Position::Tableau(0), Position::Tableau(3))),
+        Action::Move(Move(1, Position::FreeCell(0), Position::Pile(1))),
+        Action::Move(Move(1, Position::Tableau(2), Position::Flower)),
+        Action::FlipDragon(Suit::Green),
+    ];
+    for &action in &actions {
+        assert_eq!(parse_action(&format_action(action)), Ok(action));
+    }
+}
+
+#[test]
+fn test_game_roundtrip() {
+    let state = make_test_game(99, 5);
+    let text = format_game(&state);
+    assert!(text.starts_with("shenzen-solitaire game\ndeal: 99\nmoves:\n"));
+    let loaded = parse_game(&text).unwrap();
+    assert_eq!(loaded.playfield(), state.playfield());
+    assert_eq!(loaded.steps(), state.steps());
+    assert_eq!(loaded.deal(), Some(99));
+
+    let custom = GameState::new(state.playfield().clone());
+    let loaded = parse_game(&format_game(&custom)).unwrap();
+    assert_eq!(loaded.initial(), custom.initial());
+    assert_eq!(loaded.deal(), None);
+
+    assert!(parse_game("shenzen-solitaire game\ndeal: 99\nmoves:\nt1 t1 t1 t1\n").is_err());
+    assert!(parse_game("shenzen-solitaire game\ndeal: 99\n").is_err());
+    assert!(parse_game("shenzen-solitaire game\nmoves:\n").is_err());
+}
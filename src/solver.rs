@@ -0,0 +1,451 @@
+use compact::{pack_card, pack_freecell};
+use game_logic::*;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::time::{Duration, Instant};
+
+// A* search over the playfield states. Every step costs 1, so the solution that is found is
+// the shortest one possible. Like in the game itself, the automatic moves (see auto_play()) are
+// done after every action; they're safe to do, so this doesn't lose any solutions.
+
+/// How much work the solver is allowed to do before giving up.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct SearchLimits {
+    /// Maximum number of states to expand.
+    pub max_nodes: usize,
+    /// Maximum time to spend searching, if any.
+    pub max_time: Option<Duration>,
+}
+
+impl Default for SearchLimits {
+    fn default() -> SearchLimits {
+        SearchLimits { max_nodes: 200_000, max_time: None }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SolveResult {
+    /// The shortest sequence of steps winning the game, including the automatic ones.
+    Solved(Vec<Step>),
+    /// Every reachable state was examined without finding a win, so the deal can't be won.
+    Unsolvable,
+    /// The search ran out of its node budget before coming to a conclusion.
+    GaveUp,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SolveReport {
+    pub result: SolveResult,
+    /// Number of states taken off the open list and expanded.
+    pub nodes_expanded: usize,
+    /// Number of distinct (canonical) states seen.
+    pub states_seen: usize,
+    /// Number of actions possible in the expanded states in total, for the branching factor.
+    pub actions_seen: usize,
+    /// Number of dead-end subtrees: subtrees of the search tree that were searched through without
+    /// finding a win, not counting the ones inside a bigger such subtree. States that were reached
+    /// by a shorter path elsewhere don't count as part of a subtree.
+    pub dead_ends: usize,
+}
+
+// The game is over when all the cards are gone from the tableau and from the free cells.
+fn is_solved(playfield: &Playfield) -> bool {
+    playfield.tableau.iter().all(|col| col.is_empty()) &&
+        playfield.freecells.iter().all(|fc| !matches!(*fc, FreeCell::InUse(_)))
+}
+
+/// Lower bound of the number of actions still needed to win. Each action can do at most one of:
+///  - put a single numbered card on a pile,
+///  - flip the dragons of a single suit,
+///  - place the flower,
+///  - clear the cards out of the way in a single tableau column.
+///
+/// The last one is needed for every column where a numbered card sits on top of a lower card of the
+/// same suit: the lower card has to go to the pile first, so something must be moved aside.
+/// Thus the sum of those is admissible (and consistent, since it changes by at most one per action).
+pub fn heuristic(playfield: &Playfield) -> usize {
+    let mut numbers = 0;
+    let mut dragon_suits = [false; 3];
+    let mut flower = 0;
+    let mut blocked_columns = 0;
+    {
+        let mut count = |card: Card| match card {
+            Card::Number(..) => numbers += 1,
+            Card::Dragon(suit) => dragon_suits[suit.to_index()] = true,
+            Card::Flower => flower = 1,
+        };
+        for fc in playfield.freecells.iter() {
+            if let FreeCell::InUse(card) = *fc {
+                count(card);
+            }
+        }
+        for col in playfield.tableau.iter() {
+            // Lowest number seen so far in this column for each suit, going upwards.
+            let mut lowest = [10; 3];
+            let mut blocked = false;
+            for &card in col {
+                count(card);
+                if let Card::Number(suit, number) = card {
+                    blocked |= number > lowest[suit.to_index()];
+                    lowest[suit.to_index()] = lowest[suit.to_index()].min(number);
+                }
+            }
+            if blocked {
+                blocked_columns += 1;
+            }
+        }
+    }
+    numbers + flower + dragon_suits.iter().filter(|&&b| b).count() + blocked_columns
+}
+
+/// Compact key identifying a state up to symmetry: the order of the free cells, of the piles and
+/// of the tableau columns doesn't matter for how the game can continue, so they are sorted.
+pub fn canonical_key(playfield: &Playfield) -> Vec<u8> {
+    let mut freecells: Vec<u8> = playfield.freecells.iter().map(|&fc| pack_freecell(fc)).collect();
+    freecells.sort();
+    let mut piles: Vec<u8> = playfield.piles.iter().map(|p| p.map_or(0, pack_card)).collect();
+    piles.sort();
+    let mut columns: Vec<Vec<u8>> = playfield.tableau.iter()
+        .map(|col| col.iter().map(|&c| pack_card(c)).collect())
+        .collect();
+    columns.sort();
+
+    let mut key = Vec::with_capacity(48);
+    key.extend(freecells);
+    key.extend(piles);
+    key.push(playfield.flower.is_some() as u8);
+    for col in columns {
+        key.extend(col);
+        key.push(0xff);
+    }
+    key
+}
+
+struct Node {
+    parent: Option<usize>,
+    // The player's action followed by the automatic moves it triggered.
+    steps: Vec<Step>,
+    // Number of children, and how many of them haven't been searched through yet.
+    children: usize,
+    unfinished: usize,
+}
+
+impl Node {
+    fn new(parent: Option<usize>, steps: Vec<Step>) -> Node {
+        Node { parent, steps, children: 0, unfinished: 0 }
+    }
+}
+
+// Marks the subtree of the expanded node @idx as searched through, and then every ancestor whose
+// children all have been by now. Keeps @dead_ends counting the biggest of those subtrees: a
+// finished node replaces its children in the count.
+fn finish_subtree(nodes: &mut [Node], mut idx: usize, dead_ends: &mut usize) {
+    loop {
+        *dead_ends = *dead_ends + 1 - nodes[idx].children;
+        match nodes[idx].parent {
+            Some(parent) => {
+                nodes[parent].unfinished -= 1;
+                if nodes[parent].unfinished > 0 {
+                    return;
+                }
+                idx = parent;
+            }
+            None => return,
+        }
+    }
+}
+
+// Takes the node @idx, whose state turned out to have a shorter path elsewhere, out of the
+// subtree of its parent.
+fn drop_from_subtree(nodes: &mut [Node], idx: usize, dead_ends: &mut usize) {
+    if let Some(parent) = nodes[idx].parent {
+        nodes[parent].children -= 1;
+        nodes[parent].unfinished -= 1;
+        if nodes[parent].unfinished == 0 {
+            finish_subtree(nodes, parent, dead_ends);
+        }
+    }
+}
+
+struct OpenEntry {
+    f: usize,
+    g: usize,
+    node: usize,
+    playfield: Playfield,
+}
+
+impl PartialEq for OpenEntry {
+    fn eq(&self, other: &OpenEntry) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OpenEntry {}
+
+impl PartialOrd for OpenEntry {
+    fn partial_cmp(&self, other: &OpenEntry) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenEntry {
+    // BinaryHeap is a max-heap: lowest f first, and among equal f the deepest node first
+    // (it's closest to a solution).
+    fn cmp(&self, other: &OpenEntry) -> Ordering {
+        other.f.cmp(&self.f)
+            .then(self.g.cmp(&other.g))
+            .then(other.node.cmp(&self.node))
+    }
+}
+
+fn path_to(nodes: &[Node], idx: usize) -> Vec<Step> {
+    let mut chain = vec![idx];
+    while let Some(parent) = nodes[*chain.last().unwrap()].parent {
+        chain.push(parent);
+    }
+    chain.iter().rev().flat_map(|&i| nodes[i].steps.iter().cloned()).collect()
+}
+
+// Does @action followed by the automatic moves, returning the new playfield and all the steps.
+fn expand(playfield: &Playfield, action: Option<Action>) -> (Playfield, Vec<Step>) {
+    let mut steps = vec![];
+    let pf = match action {
+        Some(action) => {
+            steps.push(Step { action, automatic: false });
+            apply_action(playfield.clone(), action).unwrap()
+        }
+        None => playfield.clone(),
+    };
+    let (pf, auto_moves) = auto_play(pf);
+    steps.extend(auto_moves.into_iter().map(|m| Step { action: Action::Move(m), automatic: true }));
+    (pf, steps)
+}
+
+pub fn solve(playfield: &Playfield, limits: SearchLimits) -> SolveReport {
+    solve_weighted(playfield, limits, 1)
+}
+
+/// Searches for any way to win, not necessarily the shortest one. This is a lot faster than
+/// solve() on real deals, so it's good for hints.
+pub fn find_solution(playfield: &Playfield, limits: SearchLimits) -> SolveReport {
+    solve_weighted(playfield, limits, 2)
+}
+
+/// Like find_solution(), but as if only @freecells of the free cells could be used for parking
+/// cards: no more than that many cards are moved to the free cells at a time. Flipping dragons
+/// is allowed as usual. Running out of states to search proves only that the position can't be
+/// won that way.
+pub fn find_solution_with_freecells(playfield: &Playfield, limits: SearchLimits, freecells: usize) -> SolveReport {
+    search(playfield, limits, 2, freecells)
+}
+
+/// Like solve(), but with the heuristic multiplied by @weight. This makes the search head for the
+/// goal much more eagerly, at the price of the solution possibly not being the shortest one
+/// (it's at most @weight times longer). Running out of states to search still proves that the
+/// position can't be won.
+pub fn solve_weighted(playfield: &Playfield, limits: SearchLimits, weight: usize) -> SolveReport {
+    search(playfield, limits, weight, playfield.freecells.len())
+}
+
+// Whether making @action on @playfield leaves at most @freecells cards parked in the free cells,
+// or at least no more than there were.
+fn within_freecells(playfield: &Playfield, action: Action, freecells: usize) -> bool {
+    match action {
+        Action::Move(Move(_, from, Position::FreeCell(_))) if !matches!(from, Position::FreeCell(_)) =>
+            playfield.freecells.iter().filter(|fc| matches!(**fc, FreeCell::InUse(_))).count() < freecells,
+        _ => true,
+    }
+}
+
+// solve_weighted(), with at most @freecells cards parked in the free cells.
+fn search(playfield: &Playfield, limits: SearchLimits, weight: usize, freecells: usize) -> SolveReport {
+    let (start, start_steps) = expand(playfield, None);
+    let start_g = start_steps.len();
+    let mut nodes = vec![Node::new(None, start_steps)];
+    // Best known distance from the start for every canonical state.
+    let mut best_g: HashMap<Vec<u8>, usize> = HashMap::new();
+    let mut open = BinaryHeap::new();
+    let mut nodes_expanded = 0;
+    let mut actions_seen = 0;
+    let mut dead_ends = 0;
+    let deadline = limits.max_time.map(|t| Instant::now() + t);
+
+    best_g.insert(canonical_key(&start), start_g);
+    open.push(OpenEntry { f: start_g + weight * heuristic(&start), g: start_g, node: 0, playfield: start });
+
+    while let Some(OpenEntry { g, node, playfield, .. }) = open.pop() {
+        if best_g[&canonical_key(&playfield)] < g {
+            // A shorter path to this state was found after this entry was queued.
+            drop_from_subtree(&mut nodes, node, &mut dead_ends);
+            continue;
+        }
+        if is_solved(&playfield) {
+            return SolveReport {
+                result: SolveResult::Solved(path_to(&nodes, node)),
+                nodes_expanded,
+                states_seen: best_g.len(),
+                actions_seen,
+                dead_ends,
+            };
+        }
+        // Looking at the clock is slow compared to expanding a node, so only do it now and then.
+        let out_of_time = nodes_expanded % 1024 == 0 && deadline.is_some_and(|d| Instant::now() >= d);
+        if nodes_expanded >= limits.max_nodes || out_of_time {
+            return SolveReport {
+                result: SolveResult::GaveUp,
+                nodes_expanded,
+                states_seen: best_g.len(),
+                actions_seen,
+                dead_ends,
+            };
+        }
+        nodes_expanded += 1;
+
+        let actions: Vec<Action> = playfield.legal_moves().into_iter()
+            .filter(|&action| within_freecells(&playfield, action, freecells))
+            .collect();
+        actions_seen += actions.len();
+        let mut children = 0;
+        for action in actions {
+            let (new_pf, steps) = expand(&playfield, Some(action));
+            let key = canonical_key(&new_pf);
+            let new_g = g + steps.len();
+            if best_g.get(&key).is_some_and(|&old_g| old_g <= new_g) {
+                continue;
+            }
+            best_g.insert(key, new_g);
+            nodes.push(Node::new(Some(node), steps));
+            children += 1;
+            open.push(OpenEntry { f: new_g + weight * heuristic(&new_pf), g: new_g, node: nodes.len() - 1, playfield: new_pf });
+        }
+        nodes[node].children = children;
+        nodes[node].unfinished = children;
+        if children == 0 {
+            finish_subtree(&mut nodes, node, &mut dead_ends);
+        }
+    }
+
+    SolveReport { result: SolveResult::Unsolvable, nodes_expanded, states_seen: best_g.len(), actions_seen, dead_ends }
+}
+
+#[test]
+fn test_solve_trivial() {
+    // Black 3 must be moved out of the way, the rest happens automatically.
+    let mut pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Number(Suit::Black, 2), Card::Number(Suit::Black, 3)])
+        .with_tableau_column(1, vec![Card::Flower]);
+    pf.piles = [Some(Card::Number(Suit::Green, 9)), Some(Card::Number(Suit::Red, 9)), Some(Card::Number(Suit::Black, 1))];
+    let report = solve(&pf, SearchLimits::default());
+    match report.result {
+        SolveResult::Solved(steps) => {
+            assert_eq!(steps.len(), 4);
+            assert!(steps[0].automatic);
+            assert!(!steps[1].automatic);
+            assert!(steps[2].automatic && steps[3].automatic);
+            let mut pf = pf;
+            for step in steps {
+                pf = apply_action(pf, step.action).unwrap();
+            }
+            assert!(is_solved(&pf));
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_solve_flip_is_shortest() {
+    // Flipping the dragons at once beats parking them one by one.
+    let pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Dragon(Suit::Green)])
+        .with_tableau_column(1, vec![Card::Dragon(Suit::Green)])
+        .with_tableau_column(2, vec![Card::Dragon(Suit::Green)])
+        .with_tableau_column(3, vec![Card::Dragon(Suit::Green)]);
+    let report = solve(&pf, SearchLimits::default());
+    assert_eq!(report.result, SolveResult::Solved(vec![Step { action: Action::FlipDragon(Suit::Green), automatic: false }]));
+}
+
+#[test]
+fn test_solve_unsolvable() {
+    // Only two of the four red dragons exist, so they can never be flipped.
+    let pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Dragon(Suit::Red), Card::Number(Suit::Black, 1)])
+        .with_tableau_column(1, vec![Card::Dragon(Suit::Red)]);
+    let report = solve(&pf, SearchLimits::default());
+    assert_eq!(report.result, SolveResult::Unsolvable);
+    assert!(report.nodes_expanded > 0);
+    // All of the search tree is one big dead end.
+    assert_eq!(report.dead_ends, 1);
+}
+
+#[test]
+fn test_solve_gives_up() {
+    let pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Dragon(Suit::Red), Card::Number(Suit::Black, 1)])
+        .with_tableau_column(1, vec![Card::Dragon(Suit::Red)]);
+    let report = solve(&pf, SearchLimits { max_nodes: 1, max_time: None });
+    assert_eq!(report.result, SolveResult::GaveUp);
+    // Out of time before expanding anything.
+    let report = solve(&make_shuffled_playfield_from_seed(1), SearchLimits { max_nodes: 1_000_000, max_time: Some(Duration::from_secs(0)) });
+    assert_eq!(report.result, SolveResult::GaveUp);
+    assert_eq!(report.nodes_expanded, 0);
+}
+
+#[test]
+fn test_find_solution_with_freecells() {
+    let pf = make_shuffled_playfield_from_seed(8);
+    assert_eq!(find_solution_with_freecells(&pf, SearchLimits::default(), 0).result, SolveResult::Unsolvable);
+    match find_solution_with_freecells(&pf, SearchLimits::default(), 1).result {
+        SolveResult::Solved(steps) => {
+            let mut pf = pf;
+            for step in steps {
+                pf = apply_action(pf, step.action).unwrap();
+                assert!(pf.freecells.iter().filter(|fc| matches!(**fc, FreeCell::InUse(_))).count() <= 1);
+            }
+            assert!(pf.is_won());
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_find_solution() {
+    let pf = make_shuffled_playfield_from_seed(1);
+    match find_solution(&pf, SearchLimits::default()).result {
+        SolveResult::Solved(steps) => {
+            let mut pf = pf;
+            for step in steps {
+                pf = apply_action(pf, step.action).unwrap();
+            }
+            assert!(pf.is_won());
+        }
+        r => panic!("unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_heuristic_counts_blocked_columns() {
+    // Red 1 has to go first, so Red 3 must be moved aside before going to the pile:
+    // three actions at least.
+    let pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Number(Suit::Red, 1), Card::Number(Suit::Red, 3)]);
+    assert_eq!(heuristic(&pf), 3);
+    let pf = Playfield::empty()
+        .with_tableau_column(0, vec![Card::Number(Suit::Red, 3), Card::Number(Suit::Red, 1)]);
+    assert_eq!(heuristic(&pf), 2);
+}
+
+#[test]
+fn test_canonical_key_symmetry() {
+    let pf1 = Playfield::empty()
+        .with_freecell_cards(vec![Card::Dragon(Suit::Red)], FreeCell::Free)
+        .with_tableau_column(0, vec![Card::Number(Suit::Red, 2)])
+        .with_tableau_column(5, vec![Card::Flower]);
+    let mut pf2 = Playfield::empty()
+        .with_tableau_column(3, vec![Card::Flower])
+        .with_tableau_column(7, vec![Card::Number(Suit::Red, 2)]);
+    pf2.freecells[2] = FreeCell::InUse(Card::Dragon(Suit::Red));
+    assert_eq!(canonical_key(&pf1), canonical_key(&pf2));
+    assert!(canonical_key(&pf1) != canonical_key(&Playfield::empty()));
+}
+
+
@@ -0,0 +1,288 @@
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::fs::File;
+use std::io::{Read, Write};
+
+// Statistics of the games played, kept in a text file with one game per line:
+//
+//   1234 alice won 87 342 2 no 1700000000
+//
+// The fields are the deal number ("-" for custom boards), the player, the result (won, lost or
+// abandoned), the number of moves, the duration in seconds, the number of undos, whether hints
+// were used and when the game ended (seconds since the Unix epoch). Games of the daily challenge
+// have an extra "daily" field at the end. Empty lines and lines starting with '#' are ignored.
+// Games are appended as they end, so the file is in chronological order and can be shared by
+// several players.
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum GameResult {
+    Won,
+    Lost,
+    /// The player quit or started another game before finishing.
+    Abandoned,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GameRecord {
+    pub deal: Option<u64>,
+    pub player: String,
+    pub result: GameResult,
+    pub moves: usize,
+    pub seconds: u64,
+    /// Number of times moves were taken back: every undo, and every jump back in the history with
+    /// goto.
+    pub undos: usize,
+    pub hints_used: bool,
+    pub finished_at: u64,
+    /// Whether this was the daily challenge.
+    pub daily: bool,
+}
+
+fn format_result(result: GameResult) -> &'static str {
+    match result {
+        GameResult::Won => "won",
+        GameResult::Lost => "lost",
+        GameResult::Abandoned => "abandoned",
+    }
+}
+
+/// Player names are a single word in the file.
+pub fn sanitize_player_name(name: &str) -> String {
+    let words: Vec<&str> = name.split_whitespace().collect();
+    if words.is_empty() { "player".to_string() } else { words.join("_") }
+}
+
+pub fn format_record(record: &GameRecord) -> String {
+    format!("{} {} {} {} {} {} {} {}{}",
+            record.deal.map_or("-".to_string(), |deal| deal.to_string()),
+            sanitize_player_name(&record.player),
+            format_result(record.result),
+            record.moves,
+            record.seconds,
+            record.undos,
+            if record.hints_used { "yes" } else { "no" },
+            record.finished_at,
+            if record.daily { " daily" } else { "" })
+}
+
+pub fn parse_record(line: &str) -> Result<GameRecord, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let ([deal, player, result, moves, seconds, undos, hints, finished_at], daily) = match *fields.as_slice() {
+        [a, b, c, d, e, f, g, h] => ([a, b, c, d, e, f, g, h], false),
+        [a, b, c, d, e, f, g, h, "daily"] => ([a, b, c, d, e, f, g, h], true),
+        [_, _, _, _, _, _, _, _, extra] => return Err(format!("Invalid field '{}'", extra)),
+        _ => return Err(format!("Expected 8 fields, found {}", fields.len())),
+    };
+    let number = |s: &str, what: &str| s.parse::<u64>().map_err(|_| format!("Invalid {} '{}'", what, s));
+    Ok(GameRecord {
+        deal: match deal {
+            "-" => None,
+            _ => Some(number(deal, "deal number")?),
+        },
+        player: player.to_string(),
+        result: match result {
+            "won" => GameResult::Won,
+            "lost" => GameResult::Lost,
+            "abandoned" => GameResult::Abandoned,
+            _ => return Err(format!("Invalid result '{}'", result)),
+        },
+        moves: number(moves, "move count")? as usize,
+        seconds: number(seconds, "duration")?,
+        undos: number(undos, "undo count")? as usize,
+        hints_used: match hints {
+            "yes" => true,
+            "no" => false,
+            _ => return Err(format!("Invalid hint flag '{}'", hints)),
+        },
+        finished_at: number(finished_at, "time")?,
+        daily,
+    })
+}
+
+pub fn parse_records(text: &str) -> Result<Vec<GameRecord>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|&(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| parse_record(line).map_err(|err| format!("Line {}: {}", i + 1, err)))
+        .collect()
+}
+
+/// Reads all the records from the file at @path. A missing file just means no games yet.
+pub fn load_records(path: &str) -> Result<Vec<GameRecord>, String> {
+    let mut text = String::new();
+    match File::open(path) {
+        Ok(mut file) => file.read_to_string(&mut text)
+            .map_err(|err| format!("Can't read {}: {}", path, err))?,
+        Err(_) => return Ok(vec![]),
+    };
+    parse_records(&text).map_err(|err| format!("{}: {}", path, err))
+}
+
+pub fn append_record(path: &str, record: &GameRecord) -> Result<(), String> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)
+        .map_err(|err| format!("Can't open {}: {}", path, err))?;
+    writeln!(file, "{}", format_record(record)).map_err(|err| format!("Can't write {}: {}", path, err))
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PlayerSummary {
+    pub player: String,
+    pub played: usize,
+    pub won: usize,
+    /// Number of games won in a row most recently.
+    pub current_streak: usize,
+    pub best_streak: usize,
+}
+
+/// Sums up the records of each player, in alphabetical order. @records must be in chronological
+/// order for the streaks to make sense.
+pub fn summarize(records: &[GameRecord]) -> Vec<PlayerSummary> {
+    let mut summaries: BTreeMap<&str, PlayerSummary> = BTreeMap::new();
+    for record in records {
+        let summary = summaries.entry(&record.player).or_insert_with(|| PlayerSummary {
+            player: record.player.clone(),
+            played: 0,
+            won: 0,
+            current_streak: 0,
+            best_streak: 0,
+        });
+        summary.played += 1;
+        if record.result == GameResult::Won {
+            summary.won += 1;
+            summary.current_streak += 1;
+            summary.best_streak = summary.best_streak.max(summary.current_streak);
+        } else {
+            summary.current_streak = 0;
+        }
+    }
+    summaries.into_values().collect()
+}
+
+/// Counts the won games by their duration in whole minutes. The last bucket also has everything
+/// slower than that.
+pub fn win_time_histogram(records: &[GameRecord], buckets: usize) -> Vec<usize> {
+    let mut histogram = vec![0; buckets];
+    for record in records.iter().filter(|r| r.result == GameResult::Won) {
+        histogram[((record.seconds / 60) as usize).min(buckets - 1)] += 1;
+    }
+    histogram
+}
+
+pub fn format_seconds(seconds: u64) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+const HISTOGRAM_BUCKETS: usize = 10;
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// The text shown by the "stats" subcommand. If @deal is given, the games of that deal are listed
+/// too, fastest win first.
+pub fn format_report(records: &[GameRecord], deal: Option<u64>) -> String {
+    if records.is_empty() {
+        return "No games played yet.".to_string();
+    }
+    let mut out = String::new();
+    out.push_str(&format!("{:<16} {:>6} {:>6} {:>6} {:>8} {:>6}\n", "Player", "Played", "Won", "Win %", "Streak", "Best"));
+    for summary in summarize(records) {
+        out.push_str(&format!("{:<16} {:>6} {:>6} {:>5}% {:>8} {:>6}\n",
+                              summary.player, summary.played, summary.won, summary.won * 100 / summary.played,
+                              summary.current_streak, summary.best_streak));
+    }
+
+    let histogram = win_time_histogram(records, HISTOGRAM_BUCKETS);
+    let most = *histogram.iter().max().unwrap();
+    if most > 0 {
+        out.push_str("\nWins by time:\n");
+        for (minutes, &count) in histogram.iter().enumerate() {
+            let label = if minutes + 1 == HISTOGRAM_BUCKETS {
+                format!("{}+ min", minutes)
+            } else {
+                format!("{}-{} min", minutes, minutes + 1)
+            };
+            let bar = "#".repeat((count * HISTOGRAM_WIDTH).div_ceil(most));
+            out.push_str(&format!("  {:>9} | {} {}\n", label, bar, count));
+        }
+    }
+
+    if let Some(deal) = deal {
+        let mut games: Vec<&GameRecord> = records.iter().filter(|r| r.deal == Some(deal)).collect();
+        games.sort_by_key(|r| (r.result != GameResult::Won, r.seconds, r.moves));
+        out.push_str(&format!("\nDeal #{}:\n", deal));
+        if games.is_empty() {
+            out.push_str("  not played yet\n");
+        }
+        for r in games {
+            out.push_str(&format!("  {:<16} {:<9} {:>4} moves {:>7} {:>3} undos{}\n",
+                                  r.player, format_result(r.result), r.moves, format_seconds(r.seconds), r.undos,
+                                  if r.hints_used { ", used hints" } else { "" }));
+        }
+    }
+    out.pop();
+    out
+}
+
+#[cfg(test)]
+fn make_record(player: &str, result: GameResult, seconds: u64) -> GameRecord {
+    GameRecord {
+        deal: Some(7),
+        player: player.to_string(),
+        result,
+        moves: 50,
+        seconds,
+        undos: 0,
+        hints_used: false,
+        finished_at: 1_700_000_000,
+        daily: false,
+    }
+}
+
+#[test]
+fn test_record_roundtrip() {
+    let mut record = make_record("alice", GameResult::Won, 342);
+    record.undos = 2;
+    assert_eq!(format_record(&record), "7 alice won 50 342 2 no 1700000000");
+    assert_eq!(parse_record(&format_record(&record)), Ok(record));
+
+    let record = GameRecord { deal: None, hints_used: true, ..make_record("Bob Smith", GameResult::Abandoned, 5) };
+    assert_eq!(format_record(&record), "- Bob_Smith abandoned 50 5 0 yes 1700000000");
+    assert_eq!(parse_record(&format_record(&record)).unwrap().player, "Bob_Smith");
+
+    let record = GameRecord { daily: true, ..make_record("alice", GameResult::Lost, 5) };
+    assert_eq!(format_record(&record), "7 alice lost 50 5 0 no 1700000000 daily");
+    assert_eq!(parse_record(&format_record(&record)), Ok(record));
+
+    assert!(parse_record("7 alice won 50 342 2 no").is_err());
+    assert!(parse_record("7 alice won 50 342 2 no 1 weekly").is_err());
+    assert!(parse_record("7 alice draw 50 342 2 no 1").is_err());
+    assert!(parse_record("x alice won 50 342 2 no 1").is_err());
+    assert_eq!(parse_records("# comment\n\n7 alice won 50 342 2 no 1\n").unwrap().len(), 1);
+    assert!(parse_records("7 alice won\n").unwrap_err().starts_with("Line 1"));
+}
+
+#[test]
+fn test_summarize() {
+    let records = vec![
+        make_record("bob", GameResult::Won, 60),
+        make_record("alice", GameResult::Won, 60),
+        make_record("alice", GameResult::Won, 60),
+        make_record("alice", GameResult::Lost, 60),
+        make_record("bob", GameResult::Abandoned, 60),
+        make_record("alice", GameResult::Won, 60),
+    ];
+    assert_eq!(summarize(&records), vec![
+        PlayerSummary { player: "alice".to_string(), played: 4, won: 3, current_streak: 1, best_streak: 2 },
+        PlayerSummary { player: "bob".to_string(), played: 2, won: 1, current_streak: 0, best_streak: 1 },
+    ]);
+}
+
+#[test]
+fn test_win_time_histogram() {
+    let records = vec![
+        make_record("alice", GameResult::Won, 59),
+        make_record("alice", GameResult::Won, 60),
+        make_record("alice", GameResult::Won, 119),
+        make_record("alice", GameResult::Lost, 10),
+        make_record("alice", GameResult::Won, 3600),
+    ];
+    assert_eq!(win_time_histogram(&records, 3), vec![1, 2, 1]);
+}
@@ -0,0 +1,332 @@
+use libc;
+use std::io;
+use std::io::Write;
+use std::mem;
+use std::panic;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// Low-level terminal handling: switching the terminal to raw mode and back, and reading single
+// key presses.
+
+// The terminal settings from before raw mode was enabled, so a panic can restore them too.
+static ORIGINAL_TERMIOS: Mutex<Option<libc::termios>> = Mutex::new(None);
+
+// Keeps the terminal in raw mode and on the alternate screen for as long as this exists.
+pub struct RawTerminal {
+    _private: (),
+}
+
+impl RawTerminal {
+    // Puts the terminal on stdin into raw mode: keys are read one at a time, without echoing
+    // them, and Ctrl-C doesn't kill the program (so the terminal always gets restored).
+    // Output processing is left on, so '\n' still starts a new line.
+    pub fn enable() -> io::Result<RawTerminal> {
+        let mut termios: libc::termios = unsafe { mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let original = termios;
+        termios.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+        termios.c_iflag &= !libc::IXON;
+        termios.c_cc[libc::VMIN] = 1;
+        termios.c_cc[libc::VTIME] = 0;
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        *ORIGINAL_TERMIOS.lock().unwrap() = Some(original);
+
+        // Switch to the alternate screen, hide the cursor and turn on mouse reporting (of clicks
+        // only, in the SGR format that works for wide terminals too).
+        print!("\x1b[?1049h\x1b[?25l\x1b[?1000h\x1b[?1006h");
+        io::stdout().flush()?;
+        Ok(RawTerminal { _private: () })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+// Puts the terminal back the way it was before RawTerminal::enable(). Does nothing if raw mode
+// isn't on.
+fn restore() {
+    let original = match ORIGINAL_TERMIOS.lock() {
+        Ok(mut guard) => guard.take(),
+        Err(poisoned) => poisoned.into_inner().take(),
+    };
+    if let Some(termios) = original {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &termios) };
+        print!("\x1b[?1006l\x1b[?1000l\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+    }
+}
+
+// Makes panics restore the terminal before the panic message is printed, so that it can be read.
+pub fn restore_on_panic() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore();
+        default_hook(info);
+    }));
+}
+
+pub fn show_cursor(show: bool) {
+    print!("{}", if show { "\x1b[?25h" } else { "\x1b[?25l" });
+    io::stdout().flush().unwrap();
+}
+
+// Draws frames made of lines of text, rewriting only the lines that changed since the previous
+// frame. This doesn't flicker the way clearing the screen for every frame does.
+pub struct Screen {
+    // What's on the screen now; empty if it's unknown.
+    lines: Vec<String>,
+}
+
+impl Screen {
+    pub fn new() -> Screen {
+        Screen { lines: vec![] }
+    }
+
+    // Draws @lines from the top of the screen, and leaves the cursor on the line after them.
+    pub fn draw(&mut self, lines: Vec<String>) {
+        let mut out = String::new();
+        if self.lines.is_empty() {
+            out.push_str("\x1b[H\x1b[2J");
+        }
+        for (i, line) in lines.iter().enumerate() {
+            if self.lines.get(i) != Some(line) {
+                out.push_str(&format!("\x1b[{};1H{}\x1b[0m\x1b[K", i + 1, line));
+            }
+        }
+        // Clear anything left below, e.g. from a longer frame or a typed command.
+        out.push_str(&format!("\x1b[{};1H\x1b[J", lines.len() + 1));
+        print!("{}", out);
+        io::stdout().flush().unwrap();
+        self.lines = lines;
+    }
+}
+
+// Splits the first character or escape sequence off @s. The bool tells if it was an escape.
+fn split_first_token(s: &str) -> Option<(&str, bool, &str)> {
+    let c = s.chars().next()?;
+    let escape_len = s.strip_prefix("\x1b[")
+        .and_then(|params| params.find(|c: char| c.is_ascii_alphabetic()))
+        .map(|i| i + 3);
+    let len = escape_len.unwrap_or(c.len_utf8());
+    Some((&s[..len], escape_len.is_some(), &s[len..]))
+}
+
+// Number of characters in @s that take up space on the screen.
+pub fn visible_width(mut s: &str) -> usize {
+    let mut width = 0;
+    while let Some((_, is_escape, rest)) = split_first_token(s) {
+        if !is_escape {
+            width += 1;
+        }
+        s = rest;
+    }
+    width
+}
+
+// Draws @sprite over @line, starting at column @x. The rest of the line keeps its colours, and is
+// padded with spaces if it doesn't reach that far.
+pub fn overlay(line: &str, x: usize, sprite: &str) -> String {
+    let mut out = String::new();
+    // The escape sequences of the line up to the end of the sprite, for restoring the colours of
+    // the line after it.
+    let mut escapes = String::new();
+    let mut column = 0;
+    let mut rest = line;
+    while column < x {
+        match split_first_token(rest) {
+            Some((token, is_escape, after)) => {
+                out.push_str(token);
+                if is_escape {
+                    escapes.push_str(token);
+                } else {
+                    column += 1;
+                }
+                rest = after;
+            }
+            None => {
+                out.push(' ');
+                column += 1;
+            }
+        }
+    }
+    out.push_str("\x1b[0m");
+    out.push_str(sprite);
+    out.push_str("\x1b[0m");
+
+    let end = x + visible_width(sprite);
+    while column < end {
+        match split_first_token(rest) {
+            Some((token, is_escape, after)) => {
+                if is_escape {
+                    escapes.push_str(token);
+                } else {
+                    column += 1;
+                }
+                rest = after;
+            }
+            None => break,
+        }
+    }
+    out.push_str(&escapes);
+    out.push_str(rest);
+    out
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Escape,
+    Backspace,
+    // Ctrl-C or Ctrl-D.
+    Interrupt,
+    Char(char),
+    // The left mouse button was pressed or released at column @x, row @y (counting from 0).
+    Mouse { pressed: bool, x: usize, y: usize },
+}
+
+// Decodes the first key press in @bytes. Returns the key and the number of bytes it took, or
+// None if @bytes is empty. Unknown escape sequences and control characters are skipped over as
+// Escape and as nothing, respectively.
+pub fn parse_key(bytes: &[u8]) -> Option<(Option<Key>, usize)> {
+    let key = match *bytes.first()? {
+        b'\r' | b'\n' => Key::Enter,
+        0x7f | 0x08 => Key::Backspace,
+        0x03 | 0x04 => Key::Interrupt,
+        0x1b => return Some(parse_escape(bytes)),
+        b if (0x20..0x7f).contains(&b) => Key::Char(b as char),
+        _ => return Some((None, 1)),
+    };
+    Some((Some(key), 1))
+}
+
+// Arrow keys are sent as "ESC [ A" or "ESC O A" (and so on), depending on the terminal mode.
+// Mouse buttons are reported as "ESC [ < button ; x ; y M" when pressed and the same ending with 'm'
+// when released.
+fn parse_escape(bytes: &[u8]) -> (Option<Key>, usize) {
+    match bytes.get(1) {
+        Some(&b'[') | Some(&b'O') => {}
+        _ => return (Some(Key::Escape), 1),
+    }
+    if bytes.get(2) == Some(&b'<') {
+        return match bytes.iter().position(|&b| b == b'M' || b == b'm') {
+            Some(end) => (parse_mouse(&bytes[3..end], bytes[end] == b'M'), end + 1),
+            None => (Some(Key::Escape), 1),
+        };
+    }
+    // The sequence ends at the first letter (or ~), possibly after some numeric parameters.
+    match bytes[2..].iter().position(|&b| b.is_ascii_alphabetic() || b == b'~') {
+        Some(i) => {
+            let key = match bytes[2 + i] {
+                b'A' => Some(Key::Up),
+                b'B' => Some(Key::Down),
+                b'C' => Some(Key::Right),
+                b'D' => Some(Key::Left),
+                _ => None,
+            };
+            (key, 3 + i)
+        }
+        None => (Some(Key::Escape), 1),
+    }
+}
+
+// Decodes the "button;x;y" part of a mouse report. Only the plain left button is of interest:
+// the other buttons, the wheel, drags and modifier keys are ignored.
+fn parse_mouse(params: &[u8], pressed: bool) -> Option<Key> {
+    let params = String::from_utf8_lossy(params);
+    let numbers: Vec<usize> = params.split(';').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+    match numbers.as_slice() {
+        [0, x, y] if *x > 0 && *y > 0 => Some(Key::Mouse { pressed, x: x - 1, y: y - 1 }),
+        _ => None,
+    }
+}
+
+// Reads key presses from stdin. Keys that arrive in one go (e.g. when typing fast, or the bytes of
+// an escape sequence) are buffered.
+pub struct KeyReader {
+    pending: Vec<u8>,
+}
+
+impl KeyReader {
+    pub fn new() -> KeyReader {
+        KeyReader { pending: vec![] }
+    }
+
+    // Waits for a key press for at most @timeout, or forever if @timeout is None.
+    // Returns None if the time ran out. Reaching the end of input is an error.
+    pub fn read_key(&mut self, timeout: Option<Duration>) -> io::Result<Option<Key>> {
+        loop {
+            if let Some((key, len)) = parse_key(&self.pending) {
+                self.pending.drain(..len);
+                match key {
+                    Some(key) => return Ok(Some(key)),
+                    None => continue,
+                }
+            }
+            if !wait_for_input(timeout)? {
+                return Ok(None);
+            }
+            let mut buf = [0u8; 64];
+            let n = unsafe { libc::read(libc::STDIN_FILENO, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+            match n {
+                n if n < 0 => return Err(io::Error::last_os_error()),
+                0 => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "end of input")),
+                n => self.pending.extend_from_slice(&buf[..n as usize]),
+            }
+        }
+    }
+}
+
+// Returns whether there's something to read on stdin within @timeout.
+fn wait_for_input(timeout: Option<Duration>) -> io::Result<bool> {
+    let mut fd = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+    let timeout_ms = timeout.map_or(-1, |t| t.as_millis().min(i32::MAX as u128) as libc::c_int);
+    match unsafe { libc::poll(&mut fd, 1, timeout_ms) } {
+        n if n < 0 => {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted { Ok(false) } else { Err(err) }
+        }
+        n => Ok(n > 0),
+    }
+}
+
+#[test]
+fn test_parse_key() {
+    assert_eq!(parse_key(b""), None);
+    assert_eq!(parse_key(b"q"), Some((Some(Key::Char('q')), 1)));
+    assert_eq!(parse_key(b"\n"), Some((Some(Key::Enter), 1)));
+    assert_eq!(parse_key(b"\x7f"), Some((Some(Key::Backspace), 1)));
+    assert_eq!(parse_key(b"\x03"), Some((Some(Key::Interrupt), 1)));
+    assert_eq!(parse_key(b"\x1b[A"), Some((Some(Key::Up), 3)));
+    assert_eq!(parse_key(b"\x1bOD"), Some((Some(Key::Left), 3)));
+    assert_eq!(parse_key(b"\x1b[1;5Cx"), Some((Some(Key::Right), 6)));
+    assert_eq!(parse_key(b"\x1b"), Some((Some(Key::Escape), 1)));
+    assert_eq!(parse_key(b"\x1bx"), Some((Some(Key::Escape), 1)));
+    assert_eq!(parse_key(b"\x1b[<0;12;3M"), Some((Some(Key::Mouse { pressed: true, x: 11, y: 2 }), 10)));
+    assert_eq!(parse_key(b"\x1b[<0;1;1mq"), Some((Some(Key::Mouse { pressed: false, x: 0, y: 0 }), 9)));
+    assert_eq!(parse_key(b"\x1b[<2;5;5M"), Some((None, 9)));
+    // Unknown sequences and control characters are skipped
+    assert_eq!(parse_key(b"\x1b[5~"), Some((None, 4)));
+    assert_eq!(parse_key(b"\x01"), Some((None, 1)));
+}
+
+#[test]
+fn test_overlay() {
+    assert_eq!(visible_width("ab\x1b[31mc\x1b[0m"), 3);
+    assert_eq!(overlay("abcdef", 2, "XY"), "ab\x1b[0mXY\x1b[0mef");
+    assert_eq!(overlay("ab", 4, "X"), "ab  \x1b[0mX\x1b[0m");
+    assert_eq!(overlay("abc", 0, "X"), "\x1b[0mX\x1b[0mbc");
+    // The colour of the covered part carries on after the sprite
+    assert_eq!(overlay("a\x1b[31mbcd\x1b[0m", 1, "X"), "a\x1b[0mX\x1b[0m\x1b[31mcd\x1b[0m");
+}